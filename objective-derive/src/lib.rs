@@ -0,0 +1,146 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+/// Implement `objective::native::Objective` for a `#[repr(C)]` struct with named fields, producing
+/// an `Object` class whose members mirror the struct's fields in name, class and offset.
+///
+/// Structs that implement `Drop` are rejected, since instances destroy their members one by one
+/// and would never call it:
+///
+/// ```compile_fail
+/// use objective::native::Objective;
+///
+/// #[repr(C)]
+/// #[derive(Objective)]
+/// struct Handle {
+///     id: u64,
+/// }
+///
+/// impl Drop for Handle {
+///     fn drop(&mut self) {}
+/// }
+/// ```
+#[proc_macro_derive(Objective)]
+pub fn derive_objective(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "Objective cannot be derived for generic structs",
+        ));
+    }
+
+    let mut repr_c = false;
+    for attribute in input
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("repr"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") {
+                repr_c = true;
+            } else if meta.path.is_ident("align") || meta.path.is_ident("packed") {
+                // Objects are always laid out with the natural alignment of their members
+                return Err(meta.error("Objective does not support align or packed reprs"));
+            }
+            Ok(())
+        })?;
+    }
+    if !repr_c {
+        return Err(Error::new_spanned(
+            name,
+            "Objective can only be derived for #[repr(C)] structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    name,
+                    "Objective can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                name,
+                "Objective can only be derived for structs",
+            ))
+        }
+    };
+
+    let members = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let member = ident.to_string();
+        quote! {
+            builder.add(
+                #member.into(),
+                <#ty as ::objective::native::Objective>::class(),
+            )
+            .unwrap();
+        }
+    });
+
+    let offsets = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let member = ident.to_string();
+        quote! {
+            assert_eq!(
+                object.member(#member).unwrap().offset,
+                ::std::mem::offset_of!(#name, #ident),
+                "Member {} of {} is not laid out like the struct",
+                #member,
+                stringify!(#name),
+            );
+        }
+    });
+
+    let class_name = name.to_string();
+    Ok(quote! {
+        // Conflicts with the blanket impl, failing to compile, if the struct implements Drop
+        const _: () = {
+            trait ObjectiveMustNotImplementDrop {}
+            #[allow(drop_bounds)]
+            impl<T: ::std::ops::Drop> ObjectiveMustNotImplementDrop for T {}
+            impl ObjectiveMustNotImplementDrop for #name {}
+        };
+
+        unsafe impl ::objective::native::Objective for #name {
+            type Class = ::objective::class::object::Object;
+
+            fn class() -> ::std::sync::Arc<::objective::class::object::Object> {
+                static CLASS: ::std::sync::OnceLock<
+                    ::std::sync::Arc<::objective::class::object::Object>,
+                > = ::std::sync::OnceLock::new();
+                CLASS
+                    .get_or_init(|| {
+                        let mut builder = ::objective::class::object::Builder::new(#class_name.into());
+                        #(#members)*
+                        let object = ::objective::class::object::Object::new(builder);
+                        #(#offsets)*
+                        assert_eq!(
+                            ::objective::class::Class::layout(&object),
+                            ::std::alloc::Layout::new::<#name>(),
+                            "Class {} is not laid out like the struct",
+                            stringify!(#name),
+                        );
+                        ::std::sync::Arc::new(object)
+                    })
+                    .clone()
+            }
+        }
+    })
+}
@@ -0,0 +1,125 @@
+use objective::accessor::{Accessor, Cast, IntoAccessor, MutableCast};
+use objective::class::Class;
+use objective::instance::Instance;
+use objective::native::Objective;
+use std::alloc::Layout;
+use std::mem::offset_of;
+use std::sync::Arc;
+
+#[repr(C)]
+#[derive(Objective, Debug, Default, PartialEq)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+#[repr(C)]
+#[derive(Objective, Debug, Default, PartialEq)]
+struct Particle {
+    flag: u8,
+    id: u64,
+    position: Point,
+    history: [i16; 3],
+    name: String,
+}
+
+#[test]
+fn layout_matches_struct() {
+    let class = Particle::class();
+    assert_eq!(class.layout(), Layout::new::<Particle>());
+    assert_eq!(class.attr("id").unwrap().offset, offset_of!(Particle, id));
+    assert_eq!(
+        class.attr("position").attr("y").unwrap().offset,
+        offset_of!(Particle, position) + offset_of!(Point, y)
+    );
+    assert_eq!(
+        class.attr("history").item(2).unwrap().offset,
+        offset_of!(Particle, history) + 4
+    );
+    assert_eq!(
+        class.attr("name").unwrap().offset,
+        offset_of!(Particle, name)
+    );
+    assert!(Arc::ptr_eq(&class, &Particle::class()));
+    assert!(Arc::ptr_eq(&<[i16; 3]>::class(), &<[i16; 3]>::class()));
+}
+
+#[test]
+fn instance_reinterpreted_as_struct() {
+    let particle = Instance::new(Particle::class());
+    {
+        let mut write = particle.write().unwrap();
+        *write.attr("id").unwrap().cast::<u64>().unwrap() = 7;
+        *write
+            .attr("position")
+            .attr("y")
+            .unwrap()
+            .cast::<f32>()
+            .unwrap() = 2.5;
+        *write
+            .attr("history")
+            .item(1)
+            .unwrap()
+            .cast::<i16>()
+            .unwrap() = -3;
+        write
+            .attr("name")
+            .unwrap()
+            .cast::<String>()
+            .unwrap()
+            .push_str("seven");
+    }
+
+    assert_eq!(
+        *particle.read().unwrap().reinterpret::<Particle>().unwrap(),
+        Particle {
+            flag: 0,
+            id: 7,
+            position: Point { x: 0.0, y: 2.5 },
+            history: [0, -3, 0],
+            name: "seven".into(),
+        }
+    );
+    assert!(particle.read().unwrap().reinterpret::<Point>().is_err());
+}
+
+#[test]
+fn struct_moved_into_instance() {
+    let particle = Instance::from_native(Particle {
+        flag: 1,
+        id: 9,
+        position: Point { x: 1.0, y: -1.0 },
+        history: [1, 2, 3],
+        name: "nine".into(),
+    });
+
+    {
+        let read = particle.read().unwrap();
+        assert_eq!(*read.attr("flag").unwrap().cast::<u8>().unwrap(), 1);
+        assert_eq!(
+            *read
+                .attr("position")
+                .attr("x")
+                .unwrap()
+                .cast::<f32>()
+                .unwrap(),
+            1.0
+        );
+        assert_eq!(
+            *read.attr("history").item(2).unwrap().cast::<i16>().unwrap(),
+            3
+        );
+        assert_eq!(read.attr("name").unwrap().cast::<String>().unwrap(), "nine");
+    }
+
+    let mut write = particle.write().unwrap();
+    write
+        .reinterpret::<Particle>()
+        .unwrap()
+        .name
+        .push_str(" lives");
+    assert_eq!(
+        write.attr("name").unwrap().cast::<String>().unwrap(),
+        "nine lives"
+    );
+}
@@ -0,0 +1,83 @@
+use objective::accessor::{Accessor, IntoAccessor, MutableCast};
+use objective::class::array::Array;
+use objective::class::object::{Builder, Object};
+use objective::class::value::Value;
+use objective::class::view::{TypedView, View};
+use objective::class::Class;
+use objective::instance::Instance;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const PARTICLES: usize = 1024;
+const FRAMES: u32 = 200;
+
+fn measure(name: &str, mut frame: impl FnMut()) -> Duration {
+    frame();
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        frame();
+    }
+    let elapsed = start.elapsed() / FRAMES;
+    println!("{:<12} {:>12?} per frame", name, elapsed);
+    elapsed
+}
+
+fn main() {
+    let f32_class: Arc<dyn Class> = Arc::new(Value::<f32>::new());
+    let mut builder = Builder::new("Particle".into());
+    builder.add("x".into(), f32_class.clone()).unwrap();
+    builder.add("vx".into(), f32_class.clone()).unwrap();
+    let particle_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+    let particles_class: Arc<dyn Class> = Arc::new(Array::new(particle_class, PARTICLES).unwrap());
+    let particles = Instance::new(particles_class.clone());
+
+    let attr = measure("attr", || {
+        let mut write = particles.write().unwrap();
+        for i in 0..PARTICLES {
+            let mut particle = write.item(i).unwrap();
+            let vx = *particle
+                .reborrow()
+                .attr("vx")
+                .unwrap()
+                .cast::<f32>()
+                .unwrap();
+            *particle.attr("x").unwrap().cast::<f32>().unwrap() += black_box(vx) + 1.0;
+        }
+    });
+
+    let views: Vec<(View, View)> = (0..PARTICLES)
+        .map(|i| {
+            let particle = particles_class.item(i).unwrap();
+            (
+                particle.clone().attr("x").unwrap(),
+                particle.attr("vx").unwrap(),
+            )
+        })
+        .collect();
+    let through = measure("through", || {
+        let mut write = particles.write().unwrap();
+        for (x, vx) in views.iter() {
+            let vx = *write.through(vx).unwrap().cast::<f32>().unwrap();
+            *write.through(x).unwrap().cast::<f32>().unwrap() += black_box(vx) + 1.0;
+        }
+    });
+
+    let typed: Vec<(TypedView<f32>, TypedView<f32>)> = views
+        .iter()
+        .map(|(x, vx)| (x.typed().unwrap(), vx.typed().unwrap()))
+        .collect();
+    let compiled = measure("typed", || {
+        let mut write = particles.write().unwrap();
+        for (x, vx) in typed.iter() {
+            let vx = *vx.get_mut(&mut write);
+            *x.get_mut(&mut write) += black_box(vx) + 1.0;
+        }
+    });
+
+    println!(
+        "typed is {:.1}x faster than through, {:.1}x faster than attr",
+        through.as_secs_f64() / compiled.as_secs_f64(),
+        attr.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
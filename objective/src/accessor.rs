@@ -1,19 +1,32 @@
 use crate::error::Result;
 
+/// # Safety
+///
+/// Implementors must only hand out accessors that stay within the bounds of the data described by
+/// the class they were derived from.
 pub unsafe trait Accessor<T> {
     fn attr(&self, name: &str) -> Result<T>;
     fn item(&self, index: usize) -> Result<T>;
 }
 
+/// # Safety
+///
+/// See [`Accessor`].
 pub unsafe trait IntoAccessor<T> {
     fn attr(self, name: &str) -> Result<T>;
     fn item(self, index: usize) -> Result<T>;
 }
 
+/// # Safety
+///
+/// Implementors must only cast data whose class reports the `TypeId` of `U` as its value type.
 pub unsafe trait Cast {
     fn cast<U: 'static>(&self) -> Result<&U>;
 }
 
+/// # Safety
+///
+/// See [`Cast`]. Additionally, implementors must hold exclusive access to the underlying data.
 pub unsafe trait MutableCast {
-    fn cast<U: 'static>(&self) -> Result<&mut U>;
+    fn cast<U: 'static>(&mut self) -> Result<&mut U>;
 }
@@ -0,0 +1,50 @@
+use std::alloc::{alloc, dealloc, Layout};
+use std::sync::{Arc, OnceLock};
+
+/// Provides the memory instances are stored in, such as an arena, a shared memory region or a
+/// pool of preallocated buffers.
+///
+/// Only the inline data of an instance comes from its allocator. The elements of its lists live in
+/// separate buffers that always come from the global Rust allocator.
+///
+/// # Safety
+///
+/// `allocate()` must return either null or a pointer to at least `layout.size()` bytes aligned to
+/// `layout.align()` that stay valid and unused by anything else until the pointer is passed to
+/// `deallocate()` with the same layout.
+pub unsafe trait Allocator: Send + Sync {
+    /// Allocate memory for layout, returning null if none is available.
+    ///
+    /// # Safety
+    ///
+    /// `layout` must not be zero-sized. Instances give zero-sized data a dangling aligned pointer
+    /// instead of asking their allocator.
+    unsafe fn allocate(&self, layout: Layout) -> *mut u8;
+
+    /// # Safety
+    ///
+    /// `data` must have been returned by `allocate()` of this allocator for the same layout and not
+    /// yet deallocated.
+    unsafe fn deallocate(&self, data: *mut u8, layout: Layout);
+}
+
+/// The global Rust allocator, used by [`Instance::new`](crate::instance::Instance::new).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Global;
+
+impl Global {
+    pub fn shared() -> Arc<dyn Allocator> {
+        static GLOBAL: OnceLock<Arc<dyn Allocator>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(Global)).clone()
+    }
+}
+
+unsafe impl Allocator for Global {
+    unsafe fn allocate(&self, layout: Layout) -> *mut u8 {
+        alloc(layout)
+    }
+
+    unsafe fn deallocate(&self, data: *mut u8, layout: Layout) {
+        dealloc(data, layout);
+    }
+}
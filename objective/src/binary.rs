@@ -0,0 +1,651 @@
+//! A portable encoding of instances that carries its own schema.
+//!
+//! Encoded instances start with [`MAGIC`] and a little-endian [`VERSION`], followed by the class
+//! tree of the instance and finally its data. Every number is little-endian, lengths and counts
+//! are `u64`, strings are a length followed by UTF-8 and `isize`/`usize` are widened to 64 bits.
+//! Classes referenced more than once are written once per use, so decoding produces a tree of
+//! fresh classes with the canonical [`Objective`] classes for primitives.
+//!
+//! Decoding trusts no length in the input: lengths must be backed by enough remaining input, and
+//! what an instance may take up in memory is bounded by [`Limits`].
+
+use crate::class::array::Array;
+use crate::class::enumeration::{self, Enum};
+use crate::class::list::List;
+use crate::class::object::{self, Object};
+use crate::class::optional::Optional;
+use crate::class::Class;
+use crate::error::{Error, Result};
+use crate::instance::read::InstanceReadGuard;
+use crate::instance::Instance;
+use crate::native::Objective;
+use std::alloc::Layout;
+use std::any::TypeId;
+use std::sync::Arc;
+
+pub const MAGIC: [u8; 4] = *b"OBJV";
+pub const VERSION: u16 = 1;
+
+const OBJECT: u8 = 0x20;
+const ARRAY: u8 = 0x21;
+const LIST: u8 = 0x22;
+const OPTIONAL: u8 = 0x23;
+const ENUM: u8 = 0x24;
+
+/// Deeply nested schemas are rejected rather than overflowing the stack while decoding.
+const MAX_DEPTH: usize = 128;
+
+/// Bounds on what decoding may construct. Lengths in the input are checked against the input that
+/// remains, but elements of zero-sized classes take up no input, and a short input may still
+/// describe a lot of memory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    /// Bytes of data an instance may take up, including the elements of its lists.
+    pub size: usize,
+    /// Elements of zero-sized classes an instance may hold in its arrays and lists.
+    pub empty_elements: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            size: 1 << 30,
+            empty_elements: 1 << 20,
+        }
+    }
+}
+
+/// Encode the class tree and data of an instance.
+pub fn encode(instance: &InstanceReadGuard<'_>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    write_class(&mut bytes, &**instance.class())?;
+    // Invariant: the guard keeps the data constructed and the schema was written successfully
+    unsafe { write_data(&mut bytes, &**instance.class(), instance.data()) }?;
+    Ok(bytes)
+}
+
+/// Reconstruct the classes and data of an encoded instance within the default [`Limits`].
+pub fn decode(bytes: &[u8]) -> Result<Instance> {
+    decode_with_limits(bytes, Limits::default())
+}
+
+/// Reconstruct the classes and data of an encoded instance, failing with a `ValueError` if it would
+/// exceed limits.
+pub fn decode_with_limits(bytes: &[u8], limits: Limits) -> Result<Instance> {
+    let mut reader = Reader {
+        bytes,
+        position: 0,
+        limits,
+    };
+    if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err(Error::CorruptError(
+            "Input does not start with an encoded instance header!".into(),
+        ));
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    if version == 0 || version > VERSION {
+        return Err(Error::VersionError(format!(
+            "Cannot decode version {}, only versions up to {} are supported!",
+            version, VERSION
+        )));
+    }
+
+    let class = read_class(&mut reader, 0)?;
+    // Array lengths were checked against the input, so data that does not fit was cut off
+    let size = encoded_size(&*class).unwrap_or(usize::MAX);
+    if size > reader.remaining() {
+        return Err(Error::TruncatedError(format!(
+            "Expected at least {} bytes of data at byte {} but only {} remain!",
+            size,
+            reader.position,
+            reader.remaining()
+        )));
+    }
+    reader.reserve(&*class, 1)?;
+    let instance = Instance::new(class.clone());
+    {
+        let write = instance.write().unwrap();
+        // Invariant: data was just constructed by class and stays locked while it is filled in
+        unsafe { read_data(&mut reader, &*class, write.data()) }?;
+    }
+    if reader.position != bytes.len() {
+        return Err(Error::CorruptError(format!(
+            "Unexpected {} trailing bytes after instance data!",
+            bytes.len() - reader.position
+        )));
+    }
+    Ok(instance)
+}
+
+/// Value types that can be encoded, each identified by a type code in the schema.
+trait Primitive: Objective {
+    const CODE: u8;
+    /// The fewest bytes a value is encoded in.
+    const SIZE: usize;
+
+    fn write(&self, bytes: &mut Vec<u8>);
+    fn read(reader: &mut Reader) -> Result<Self>;
+}
+
+macro_rules! numbers {
+    ($($type:ty => $code:literal),*) => {
+        $(
+            impl Primitive for $type {
+                const CODE: u8 = $code;
+                const SIZE: usize = size_of::<$type>();
+
+                fn write(&self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read(reader: &mut Reader) -> Result<Self> {
+                    Ok(<$type>::from_le_bytes(reader.array()?))
+                }
+            }
+        )*
+    };
+}
+
+numbers!(
+    i8 => 0x03, i16 => 0x04, i32 => 0x05, i64 => 0x06, i128 => 0x07,
+    u8 => 0x09, u16 => 0x0a, u32 => 0x0b, u64 => 0x0c, u128 => 0x0d,
+    f32 => 0x0f, f64 => 0x10
+);
+
+impl Primitive for bool {
+    const CODE: u8 = 0x01;
+    const SIZE: usize = 1;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.push(*self as u8);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self> {
+        match reader.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(reader.corrupt(format!("Invalid bool {}", byte))),
+        }
+    }
+}
+
+impl Primitive for char {
+    const CODE: u8 = 0x02;
+    const SIZE: usize = u32::SIZE;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        (*self as u32).write(bytes);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let value = u32::read(reader)?;
+        char::from_u32(value).ok_or_else(|| reader.corrupt(format!("Invalid char {:#x}", value)))
+    }
+}
+
+impl Primitive for isize {
+    const CODE: u8 = 0x08;
+    const SIZE: usize = i64::SIZE;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        (*self as i64).write(bytes);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let value = i64::read(reader)?;
+        isize::try_from(value)
+            .map_err(|_| reader.corrupt(format!("{} does not fit in isize", value)))
+    }
+}
+
+impl Primitive for usize {
+    const CODE: u8 = 0x0e;
+    const SIZE: usize = u64::SIZE;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        (*self as u64).write(bytes);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let value = u64::read(reader)?;
+        usize::try_from(value)
+            .map_err(|_| reader.corrupt(format!("{} does not fit in usize", value)))
+    }
+}
+
+impl Primitive for String {
+    const CODE: u8 = 0x11;
+    const SIZE: usize = usize::SIZE;
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        write_name(bytes, self);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let length = usize::read(reader)?;
+        let position = reader.position;
+        String::from_utf8(reader.take(length)?.to_vec()).map_err(|_| {
+            Error::CorruptError(format!("Invalid UTF-8 in string at byte {}!", position))
+        })
+    }
+}
+
+macro_rules! primitives {
+    ($($type:ty),*) => {
+        fn primitive_code(type_id: TypeId) -> Option<u8> {
+            $(
+                if type_id == TypeId::of::<$type>() {
+                    return Some(<$type>::CODE);
+                }
+            )*
+            None
+        }
+
+        fn primitive_size(type_id: TypeId) -> Option<usize> {
+            $(
+                if type_id == TypeId::of::<$type>() {
+                    return Some(<$type>::SIZE);
+                }
+            )*
+            None
+        }
+
+        fn primitive_class(code: u8) -> Option<Arc<dyn Class>> {
+            $(
+                if code == <$type>::CODE {
+                    return Some(<$type>::class());
+                }
+            )*
+            None
+        }
+
+        /// # Safety
+        ///
+        /// `data` must have been constructed by a value class of type_id.
+        unsafe fn write_primitive(bytes: &mut Vec<u8>, type_id: TypeId, data: *const u8) -> bool {
+            $(
+                if type_id == TypeId::of::<$type>() {
+                    (*data.cast::<$type>()).write(bytes);
+                    return true;
+                }
+            )*
+            false
+        }
+
+        /// # Safety
+        ///
+        /// `data` must have been constructed by a value class of type_id.
+        unsafe fn read_primitive(reader: &mut Reader, type_id: TypeId, data: *mut u8) -> Result<bool> {
+            $(
+                if type_id == TypeId::of::<$type>() {
+                    *data.cast::<$type>() = <$type>::read(reader)?;
+                    return Ok(true);
+                }
+            )*
+            Ok(false)
+        }
+    };
+}
+
+primitives!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, String
+);
+
+fn unsupported(class: &dyn Class) -> Error {
+    Error::TypeError(format!("Class {:?} cannot be encoded!", class))
+}
+
+fn write_name(bytes: &mut Vec<u8>, name: &str) {
+    name.len().write(bytes);
+    bytes.extend_from_slice(name.as_bytes());
+}
+
+fn write_class(bytes: &mut Vec<u8>, class: &dyn Class) -> Result<()> {
+    if let Some(type_id) = class.value() {
+        bytes.push(primitive_code(type_id).ok_or_else(|| unsupported(class))?);
+    } else if let Some(object) = class.downcast_ref::<Object>() {
+        bytes.push(OBJECT);
+        write_object(bytes, object)?;
+    } else if let Some(array) = class.downcast_ref::<Array>() {
+        bytes.push(ARRAY);
+        array.length.write(bytes);
+        write_class(bytes, &*array.element)?;
+    } else if let Some(list) = class.downcast_ref::<List>() {
+        bytes.push(LIST);
+        write_class(bytes, &*list.element)?;
+    } else if let Some(optional) = class.downcast_ref::<Optional>() {
+        bytes.push(OPTIONAL);
+        write_class(bytes, &*optional.inner)?;
+    } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+        bytes.push(ENUM);
+        write_name(bytes, &enumeration.name);
+        enumeration.variants().len().write(bytes);
+        for variant in enumeration.variants() {
+            write_name(bytes, &variant.name);
+            match &variant.class {
+                Some(class) => {
+                    true.write(bytes);
+                    write_class(bytes, &**class)?;
+                }
+                None => false.write(bytes),
+            }
+        }
+    } else {
+        return Err(unsupported(class));
+    }
+    Ok(())
+}
+
+// Only the members an object adds are written, inherited ones come with its base
+fn write_object(bytes: &mut Vec<u8>, object: &Object) -> Result<()> {
+    write_name(bytes, &object.name);
+    let inherited = match &object.base {
+        Some(base) => {
+            let base = base
+                .downcast_ref::<Object>()
+                .ok_or_else(|| unsupported(&**base))?;
+            true.write(bytes);
+            write_object(bytes, base)?;
+            base.members().len()
+        }
+        None => {
+            false.write(bytes);
+            0
+        }
+    };
+
+    let members = &object.members()[inherited..];
+    members.len().write(bytes);
+    for member in members {
+        write_name(bytes, &member.name);
+        write_class(bytes, &*member.class)?;
+    }
+    Ok(())
+}
+
+/// # Safety
+///
+/// `data` must have been constructed by class.
+unsafe fn write_data(bytes: &mut Vec<u8>, class: &dyn Class, data: *mut u8) -> Result<()> {
+    if let Some(type_id) = class.value() {
+        if !write_primitive(bytes, type_id, data) {
+            return Err(unsupported(class));
+        }
+    } else if let Some(object) = class.downcast_ref::<Object>() {
+        for member in object.members() {
+            write_data(bytes, &*member.class, data.add(member.offset))?;
+        }
+    } else if let Some(array) = class.downcast_ref::<Array>() {
+        let stride = array.element.stride();
+        for i in 0..array.length {
+            write_data(bytes, &*array.element, data.add(stride * i))?;
+        }
+    } else if let Some(list) = class.downcast_ref::<List>() {
+        let length = list.len(data);
+        length.write(bytes);
+        for i in 0..length {
+            write_data(bytes, &*list.element, list.item_at(data, i)?.data)?;
+        }
+    } else if let Some(optional) = class.downcast_ref::<Optional>() {
+        let set = optional.is_set(data);
+        set.write(bytes);
+        if set {
+            write_data(bytes, &*optional.inner, data.add(optional.offset))?;
+        }
+    } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+        enumeration.discriminant(data).write(bytes);
+        if let Some(payload) = &enumeration.variant(data).class {
+            write_data(bytes, &**payload, data.add(enumeration.offset))?;
+        }
+    } else {
+        return Err(unsupported(class));
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// What is left of the limits after the data reserved so far.
+    limits: Limits,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        let remaining = self.remaining();
+        if length > remaining {
+            return Err(Error::TruncatedError(format!(
+                "Expected {} bytes at byte {} but only {} remain!",
+                length, self.position, remaining
+            )));
+        }
+        let taken = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Describe a problem with the value that ends at the current position.
+    fn corrupt(&self, message: String) -> Error {
+        Error::CorruptError(format!("{} before byte {}!", message, self.position))
+    }
+
+    /// Account for count instances of class before constructing them, so that lengths which the
+    /// remaining input cannot back or which exceed the limits are rejected up front.
+    fn reserve(&mut self, class: &dyn Class, count: usize) -> Result<()> {
+        let remaining = self.remaining();
+        let encoded = encoded_size(class).and_then(|size| size.checked_mul(count));
+        if encoded.is_none_or(|encoded| encoded > remaining) {
+            return Err(self.corrupt(format!(
+                "{} of {:?} cannot be encoded in the {} remaining bytes",
+                count, class, remaining
+            )));
+        }
+
+        let exceeded = || {
+            Error::ValueError(format!(
+                "Decoding {} of {:?} before byte {} exceeds the limits!",
+                count, class, self.position
+            ))
+        };
+        let size = class.stride().checked_mul(count).ok_or_else(exceeded)?;
+        let empty = empty_elements(class)
+            .and_then(|elements| elements.checked_add((class.stride() == 0) as usize))
+            .and_then(|elements| elements.checked_mul(count))
+            .ok_or_else(exceeded)?;
+        let limits = Limits {
+            size: self.limits.size.checked_sub(size).ok_or_else(exceeded)?,
+            empty_elements: self
+                .limits
+                .empty_elements
+                .checked_sub(empty)
+                .ok_or_else(exceeded)?,
+        };
+        self.limits = limits;
+        Ok(())
+    }
+}
+
+/// The fewest bytes the data of class is encoded in, or None if that does not fit in usize.
+fn encoded_size(class: &dyn Class) -> Option<usize> {
+    if let Some(type_id) = class.value() {
+        Some(primitive_size(type_id).unwrap_or(0))
+    } else if let Some(object) = class.downcast_ref::<Object>() {
+        object.members().iter().try_fold(0usize, |size, member| {
+            size.checked_add(encoded_size(&*member.class)?)
+        })
+    } else if let Some(array) = class.downcast_ref::<Array>() {
+        encoded_size(&*array.element)?.checked_mul(array.length)
+    } else if class.downcast_ref::<List>().is_some() {
+        Some(usize::SIZE)
+    } else if class.downcast_ref::<Optional>().is_some() {
+        Some(bool::SIZE)
+    } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+        let payload = enumeration
+            .variants()
+            .iter()
+            .map(|variant| {
+                variant
+                    .class
+                    .as_ref()
+                    .map_or(Some(0), |class| encoded_size(&**class))
+            })
+            .try_fold(usize::MAX, |least, size| Some(least.min(size?)))?;
+        usize::SIZE.checked_add(payload)
+    } else {
+        Some(0)
+    }
+}
+
+/// How many elements of zero-sized classes constructing class may take, whichever variant or
+/// optional value is set, or None if that does not fit in usize. Lists start out empty.
+fn empty_elements(class: &dyn Class) -> Option<usize> {
+    if let Some(object) = class.downcast_ref::<Object>() {
+        object
+            .members()
+            .iter()
+            .try_fold(0usize, |elements, member| {
+                elements.checked_add(empty_elements(&*member.class)?)
+            })
+    } else if let Some(array) = class.downcast_ref::<Array>() {
+        let empty = (array.element.stride() == 0) as usize;
+        empty_elements(&*array.element)?
+            .checked_add(empty)?
+            .checked_mul(array.length)
+    } else if let Some(optional) = class.downcast_ref::<Optional>() {
+        empty_elements(&*optional.inner)
+    } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+        enumeration
+            .variants()
+            .iter()
+            .filter_map(|variant| variant.class.as_ref())
+            .try_fold(0usize, |most, class| {
+                Some(most.max(empty_elements(&**class)?))
+            })
+    } else {
+        Some(0)
+    }
+}
+
+fn read_class(reader: &mut Reader, depth: usize) -> Result<Arc<dyn Class>> {
+    if depth > MAX_DEPTH {
+        return Err(reader.corrupt(format!("Schema nested deeper than {}", MAX_DEPTH)));
+    }
+
+    let code = reader.byte()?;
+    let class: Arc<dyn Class> = match code {
+        OBJECT => read_object(reader, depth)?,
+        ARRAY => {
+            let length = usize::read(reader)?;
+            let element = read_class(reader, depth + 1)?;
+            // The data of every element follows the schema, so it must fit in the remaining input
+            let encoded = encoded_size(&*element).and_then(|size| size.checked_mul(length));
+            if encoded.is_none_or(|encoded| encoded > reader.remaining()) {
+                return Err(reader.corrupt(format!("Array of length {} is too large", length)));
+            }
+            Arc::new(
+                Array::new(element, length).map_err(|error| reader.corrupt(error.to_string()))?,
+            )
+        }
+        LIST => Arc::new(List::new(read_class(reader, depth + 1)?)),
+        OPTIONAL => Arc::new(Optional::new(read_class(reader, depth + 1)?)),
+        ENUM => {
+            let mut builder = enumeration::Builder::new(String::read(reader)?);
+            let count = usize::read(reader)?;
+            for _ in 0..count {
+                let name = String::read(reader)?;
+                let class = match bool::read(reader)? {
+                    true => Some(read_class(reader, depth + 1)?),
+                    false => None,
+                };
+                builder
+                    .add(name, class)
+                    .map_err(|error| reader.corrupt(error.to_string()))?;
+            }
+            Arc::new(Enum::new(builder).map_err(|error| reader.corrupt(error.to_string()))?)
+        }
+        code => primitive_class(code)
+            .ok_or_else(|| reader.corrupt(format!("Unknown type code {:#04x}", code)))?,
+    };
+
+    if Layout::from_size_align(class.size(), class.align()).is_err() {
+        return Err(reader.corrupt(format!("Class {:?} is too large", class)));
+    }
+    Ok(class)
+}
+
+fn read_object(reader: &mut Reader, depth: usize) -> Result<Arc<Object>> {
+    let name = String::read(reader)?;
+    let mut builder = match bool::read(reader)? {
+        true => object::Builder::new_inherit(name, read_object(reader, depth + 1)?),
+        false => object::Builder::new(name),
+    };
+
+    let count = usize::read(reader)?;
+    for _ in 0..count {
+        let name = String::read(reader)?;
+        let class = read_class(reader, depth + 1)?;
+        builder
+            .add(name, class)
+            .map_err(|error| reader.corrupt(error.to_string()))?;
+    }
+    Ok(Arc::new(Object::new(builder)))
+}
+
+/// # Safety
+///
+/// `data` must have been constructed by class.
+unsafe fn read_data(reader: &mut Reader, class: &dyn Class, data: *mut u8) -> Result<()> {
+    if let Some(type_id) = class.value() {
+        if !read_primitive(reader, type_id, data)? {
+            return Err(unsupported(class));
+        }
+    } else if let Some(object) = class.downcast_ref::<Object>() {
+        for member in object.members() {
+            read_data(reader, &*member.class, data.add(member.offset))?;
+        }
+    } else if let Some(array) = class.downcast_ref::<Array>() {
+        let stride = array.element.stride();
+        for i in 0..array.length {
+            read_data(reader, &*array.element, data.add(stride * i))?;
+        }
+    } else if let Some(list) = class.downcast_ref::<List>() {
+        let length = usize::read(reader)?;
+        reader.reserve(&*list.element, length)?;
+        for _ in 0..length {
+            read_data(reader, &*list.element, list.push(data)?)?;
+        }
+    } else if let Some(optional) = class.downcast_ref::<Optional>() {
+        if bool::read(reader)? {
+            read_data(reader, &*optional.inner, optional.set(data))?;
+        }
+    } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+        let index = usize::read(reader)?;
+        let Some(variant) = enumeration.variants().get(index) else {
+            return Err(reader.corrupt(format!(
+                "Enum {} has no variant {}",
+                enumeration.name, index
+            )));
+        };
+        enumeration.switch(data, &variant.name)?;
+        if let Some(payload) = &variant.class {
+            read_data(reader, &**payload, data.add(enumeration.offset))?;
+        }
+    } else {
+        return Err(unsupported(class));
+    }
+    Ok(())
+}
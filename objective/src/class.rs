@@ -1,34 +1,258 @@
 pub mod array;
+pub mod child;
+pub mod columnar;
+pub mod enumeration;
 pub mod id;
 pub mod lens;
+pub mod list;
 pub mod object;
+pub mod optional;
+pub mod pointer;
+mod structure;
 pub mod value;
 pub mod view;
 
 use crate::accessor::Accessor;
+use crate::class::child::Child;
 use crate::class::id::Id;
 use crate::class::lens::Lens;
+use crate::class::pointer::Pointer;
+use crate::error::{Error, Result};
 use std::alloc::Layout;
-use std::any::TypeId;
+use std::any::{Any, TypeId};
+use std::fmt::{Display, Formatter};
+use std::hash::Hasher;
+use std::sync::Arc;
 
 pub trait Unique {
     fn id(&self) -> &Id;
 }
 
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: Any + Send + Sync> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+/// # Safety
+///
+/// See [`Class`].
 pub unsafe trait Metaclass {
+    /// # Safety
+    ///
+    /// `data` must point to a buffer of at least `size()` bytes aligned to `align()`.
     unsafe fn construct(&self, data: *mut u8);
+
+    /// # Safety
+    ///
+    /// `data` must have been passed to `construct()` of this class and not yet destroyed.
     unsafe fn destroy(&self, data: *mut u8);
+
+    /// Whether this class implements one of the optional operations below.
+    fn supports(&self, operation: Operation) -> bool {
+        let _ = operation;
+        false
+    }
+
+    /// Construct a deep copy of source in data. If an error is returned, data is left unconstructed.
+    ///
+    /// # Safety
+    ///
+    /// `source` must have been constructed by this class and `data` must satisfy `construct()`.
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        let _ = (source, data);
+        Err(Operation::Clone.unsupported())
+    }
+
+    /// # Safety
+    ///
+    /// `left` and `right` must have been constructed by this class.
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        let _ = (left, right);
+        Err(Operation::Eq.unsupported())
+    }
+
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        let _ = (data, state);
+        Err(Operation::Hash.unsupported())
+    }
+}
+
+/// Operations that classes may optionally implement on constructed data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operation {
+    Clone,
+    Eq,
+    Hash,
+}
+
+impl Operation {
+    pub(crate) fn unsupported(self) -> Error {
+        Error::TypeError(format!("Class does not support {}!", self))
+    }
 }
 
-// Unsafe: this trait is fucked up bruh
-//   - construct() expects a buffer with length >= size()
-//   - construct() guarantees that data may be cast as the corresponding T of id()
-//   - destroy() expects that data has been constructed() by this type
-pub unsafe trait Class: Metaclass + Accessor<Lens> + Unique + std::fmt::Debug {
+impl Display for Operation {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Clone => write!(formatter, "cloning"),
+            Operation::Eq => write!(formatter, "equality"),
+            Operation::Hash => write!(formatter, "hashing"),
+        }
+    }
+}
+
+/// Describes the layout of some data and how to construct, access and destroy it.
+///
+/// Classes are shared between threads, e.g. by the class caches of native types and by registries,
+/// so they must be `Send` and `Sync`. This says nothing about the data they describe: a
+/// [`Value`](value::Value) class is both for any type.
+///
+/// # Safety
+///
+/// Instances and references trust classes to stay in bounds, so implementors must uphold:
+///
+/// - `layout()` has the size of `size()` and the alignment of `align()`, and `align()` is a power
+///   of two.
+/// - `construct()` fully initializes the first `size()` bytes of data and nothing beyond.
+/// - If `value()` returns the `TypeId` of `T`, constructed data is a valid `T`.
+/// - `destroy()` releases everything `construct()` acquired, and leaves data unconstructed.
+/// - Every lens and pointer handed out by the accessors and `children()` stays within `size()`
+///   bytes of data and describes data constructed by its class.
+pub unsafe trait Class:
+    Metaclass + Accessor<Lens> + Unique + AsAny + Send + Sync + std::fmt::Debug
+{
     fn size(&self) -> usize;
     fn align(&self) -> usize;
     fn layout(&self) -> Layout;
+
+    /// The distance between consecutive elements of this class, i.e. size() rounded up to align().
+    fn stride(&self) -> usize {
+        align(self.size(), self.align())
+    }
+
     fn value(&self) -> Option<TypeId> {
         None
     }
+
+    /// The class this one inherits its leading members from.
+    fn base(&self) -> Option<&Arc<dyn Class>> {
+        None
+    }
+
+    /// Whether this class is other or inherits from it, in which case every view of other is also
+    /// valid for instances of this class.
+    fn is_subclass_of(&self, other: &dyn Class) -> bool {
+        self.id() == other.id() || self.base().is_some_and(|base| base.is_subclass_of(other))
+    }
+
+    /// Children that live at a fixed offset in every instance, such as object members and array
+    /// elements. Children whose location depends on instance data, like list elements, are not
+    /// included.
+    fn children(&self) -> Vec<Child> {
+        Vec::new()
+    }
+
+    /// Resolve an attribute against constructed data rather than the class alone. Classes whose
+    /// children do not live at a fixed offset, like a list's heap buffer, override this.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    unsafe fn attr_at(&self, data: *mut u8, name: &str) -> Result<Pointer> {
+        Accessor::<Lens>::attr(self, name).map(|lens| Pointer {
+            class: lens.class,
+            data: data.add(lens.offset),
+        })
+    }
+
+    /// See [`Class::attr_at`].
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    unsafe fn item_at(&self, data: *mut u8, index: usize) -> Result<Pointer> {
+        Accessor::<Lens>::item(self, index).map(|lens| Pointer {
+            class: lens.class,
+            data: data.add(lens.offset),
+        })
+    }
+}
+
+impl dyn Class + '_ {
+    pub fn downcast_ref<T: Class + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref::<T>()
+    }
+
+    pub fn downcast_arc<T: Class + 'static>(self: Arc<Self>) -> Option<Arc<T>> {
+        self.into_any().downcast::<T>().ok()
+    }
+
+    /// Whether both classes lay out the same members at the same offsets with the same value
+    /// types, regardless of their names and identities.
+    pub fn is_identical_to(&self, other: &dyn Class) -> bool {
+        structure::identical(self, other)
+    }
+
+    /// Whether other starts with exactly the layout of this class, so that views of this class
+    /// also apply to data of other.
+    pub fn is_prefix_of(&self, other: &dyn Class) -> bool {
+        structure::prefix(self, other)
+    }
+
+    /// Whether views built against origin may be applied to data of this class, either because
+    /// it inherits from origin or because it is structurally compatible.
+    pub fn is_compatible_with(&self, origin: &dyn Class) -> bool {
+        self.is_subclass_of(origin) || origin.is_prefix_of(self)
+    }
+}
+
+/// Round offset up to the next multiple of align.
+pub fn align(offset: usize, align: usize) -> usize {
+    if align == 0 {
+        offset
+    } else {
+        offset.div_ceil(align) * align
+    }
+}
+
+/// Place a member of class after size bytes of members aligned to alignment, returning its offset
+/// and the new size, or None if the result padded to its alignment would not fit in an allocation.
+pub(crate) fn place(size: usize, alignment: usize, class: &dyn Class) -> Option<(usize, usize)> {
+    let offset = align(size, class.align());
+    let end = offset.checked_add(class.size())?;
+    Layout::from_size_align(end, alignment.max(class.align())).ok()?;
+    Some((offset, end))
+}
+
+/// Data together with the class that constructed it, as walked by formatting and serde.
+///
+/// Every child address derived from constructed data through its class, e.g. a member offset or
+/// an array element, is constructed by the child's class too, so walkers may descend into
+/// children without further checks.
+#[derive(Clone, Copy)]
+pub(crate) struct Constructed<'a, P> {
+    pub class: &'a dyn Class,
+    pub data: P,
+}
+
+impl<'a, P> Constructed<'a, P> {
+    /// # Safety
+    ///
+    /// `data` must have been constructed by class and outlive the returned value.
+    pub unsafe fn new(class: &'a dyn Class, data: P) -> Self {
+        Constructed { class, data }
+    }
 }
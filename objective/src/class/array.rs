@@ -1,9 +1,11 @@
 use crate::accessor::Accessor;
+use crate::class::child::{Child, Key};
 use crate::class::id::Id;
 use crate::class::lens::Lens;
-use crate::class::{Class, Metaclass, Unique};
+use crate::class::{Class, Metaclass, Operation, Unique};
 use crate::error::{Error, Result};
 use std::alloc::Layout;
+use std::hash::Hasher;
 use std::sync::Arc;
 
 pub struct Array {
@@ -14,14 +16,24 @@ pub struct Array {
 }
 
 impl Array {
-    pub fn new(element: Arc<dyn Class>, length: usize) -> Self {
-        let size = element.size() * length;
-        Self {
+    /// Fails if the array would be too large for any allocation.
+    pub fn new(element: Arc<dyn Class>, length: usize) -> Result<Self> {
+        let size = element
+            .stride()
+            .checked_mul(length)
+            .filter(|size| Layout::from_size_align(*size, element.align()).is_ok())
+            .ok_or_else(|| {
+                Error::TypeError(format!(
+                    "Array of length {} of {:?} is too large",
+                    length, element
+                ))
+            })?;
+        Ok(Self {
             id: Id::new(),
             element,
             length,
             size,
-        }
+        })
     }
 }
 
@@ -49,7 +61,7 @@ unsafe impl Accessor<Lens> for Array {
         if index < self.length {
             Ok(Lens {
                 class: self.element.clone(),
-                offset: self.element.size() * index,
+                offset: self.element.stride() * index,
             })
         } else {
             Err(Error::IndexError(format!(
@@ -64,7 +76,7 @@ unsafe impl Metaclass for Array {
     unsafe fn construct(&self, data: *mut u8) {
         for i in 0..self.length {
             unsafe {
-                let address = data.add(self.element.size() * i);
+                let address = data.add(self.element.stride() * i);
                 self.element.construct(address);
             }
         }
@@ -73,11 +85,54 @@ unsafe impl Metaclass for Array {
     unsafe fn destroy(&self, data: *mut u8) {
         for i in (0..self.length).rev() {
             unsafe {
-                let address = data.add(self.element.size() * i);
+                let address = data.add(self.element.stride() * i);
                 self.element.destroy(address);
             }
         }
     }
+
+    fn supports(&self, operation: Operation) -> bool {
+        self.element.supports(operation)
+    }
+
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        let stride = self.element.stride();
+        for i in 0..self.length {
+            let offset = stride * i;
+            if let Err(error) = self
+                .element
+                .clone_data(source.add(offset), data.add(offset))
+            {
+                // Leave data unconstructed by destroying the elements cloned so far
+                for j in (0..i).rev() {
+                    self.element.destroy(data.add(stride * j));
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        let stride = self.element.stride();
+        for i in 0..self.length {
+            if !self
+                .element
+                .eq_data(left.add(stride * i), right.add(stride * i))?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        let stride = self.element.stride();
+        for i in 0..self.length {
+            self.element.hash_data(data.add(stride * i), state)?;
+        }
+        Ok(())
+    }
 }
 
 unsafe impl Class for Array {
@@ -89,9 +144,17 @@ unsafe impl Class for Array {
         self.element.align()
     }
 
+    fn children(&self) -> Vec<Child> {
+        (0..self.length)
+            .map(|i| Child {
+                key: Key::Index(i),
+                class: self.element.clone(),
+                offset: self.element.stride() * i,
+            })
+            .collect()
+    }
+
     fn layout(&self) -> Layout {
-        self.element.layout()
+        Layout::from_size_align(self.size, self.align()).unwrap()
     }
 }
-
-
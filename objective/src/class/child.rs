@@ -0,0 +1,45 @@
+use crate::class::Class;
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Key {
+    Name(String),
+    Index(usize),
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Name(name) => write!(formatter, "{}", name),
+            Key::Index(index) => write!(formatter, "[{}]", index),
+        }
+    }
+}
+
+/// A child of a class located at a fixed offset from the start of its parent.
+#[derive(Clone)]
+pub struct Child {
+    pub key: Key,
+    pub class: Arc<dyn Class>,
+    pub offset: usize,
+}
+
+impl Child {
+    pub fn size(&self) -> usize {
+        self.class.size()
+    }
+
+    pub fn align(&self) -> usize {
+        self.class.align()
+    }
+}
+
+impl std::fmt::Debug for Child {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{}: {:?} @ {}",
+            self.key, self.class, self.offset
+        )
+    }
+}
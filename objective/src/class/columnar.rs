@@ -0,0 +1,368 @@
+use crate::accessor::Accessor;
+use crate::class::array::Array;
+use crate::class::child::{Child, Key};
+use crate::class::id::Id;
+use crate::class::lens::Lens;
+use crate::class::object::Object;
+use crate::class::{align, place, Class, Metaclass, Operation, Unique};
+use crate::error::{Error, Result};
+use std::alloc::Layout;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::{Arc, Mutex};
+
+/// A fixed number of objects stored member by member: each member of the element class gets its
+/// own contiguous column, so scanning one member touches no others.
+///
+/// Columns are available as arrays by member name. Items are [`Row`]s, which resolve attributes
+/// into the columns, so `item(i).attr("x")` works as it does for an array of objects.
+pub struct Columnar {
+    id: Id,
+    shared: Arc<Shared>,
+    rows: Mutex<HashMap<usize, Arc<Row>>>,
+}
+
+/// One member's column, an array of the member's class.
+pub struct Column {
+    pub name: String,
+    pub class: Arc<Array>,
+    pub offset: usize,
+}
+
+struct Shared {
+    element: Arc<Object>,
+    length: usize,
+    columns: Vec<Column>,
+    lookup: HashMap<String, usize>,
+    size: usize,
+    align: usize,
+}
+
+impl Columnar {
+    /// Fails if the columns would be too large for any allocation.
+    pub fn new(element: Arc<Object>, length: usize) -> Result<Self> {
+        let mut columns = Vec::new();
+        let mut lookup = HashMap::new();
+        let mut size = 0;
+        let alignment = element.align();
+        for member in element.members() {
+            let class = Arc::new(Array::new(member.class.clone(), length)?);
+            let (offset, end) = place(size, alignment, &*class).ok_or_else(|| {
+                Error::TypeError(format!("Columnar {:?}[{}] is too large", element, length))
+            })?;
+            size = end;
+            lookup.insert(member.name.clone(), columns.len());
+            columns.push(Column {
+                name: member.name.clone(),
+                class,
+                offset,
+            });
+        }
+        Ok(Columnar {
+            id: Id::new(),
+            shared: Arc::new(Shared {
+                length,
+                columns,
+                lookup,
+                size: align(size, alignment),
+                align: alignment,
+                element,
+            }),
+            rows: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn element(&self) -> &Arc<Object> {
+        &self.shared.element
+    }
+
+    pub fn length(&self) -> usize {
+        self.shared.length
+    }
+
+    /// Columns in the order of the element's members.
+    pub fn columns(&self) -> &[Column] {
+        &self.shared.columns
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.shared.column(name)
+    }
+
+    /// The class of one item, whose data is that of the whole container. See [`Row`].
+    ///
+    /// Rows are created on first access and kept, so every access to an index yields the same row.
+    pub fn row(&self, index: usize) -> Result<Arc<Row>> {
+        if index < self.shared.length {
+            let mut rows = self.rows.lock().unwrap();
+            let row = rows.entry(index).or_insert_with(|| {
+                Arc::new(Row {
+                    id: Id::new(),
+                    shared: self.shared.clone(),
+                    index,
+                })
+            });
+            Ok(row.clone())
+        } else {
+            Err(Error::IndexError(format!(
+                "Columnar index {} out of bounds {}",
+                index, self.shared.length
+            )))
+        }
+    }
+}
+
+impl Shared {
+    fn column(&self, name: &str) -> Option<&Column> {
+        self.lookup.get(name).map(|index| &self.columns[*index])
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::from_size_align(self.size, self.align).unwrap()
+    }
+
+    unsafe fn construct(&self, data: *mut u8) {
+        for column in &self.columns {
+            column.class.construct(data.add(column.offset));
+        }
+    }
+
+    unsafe fn destroy(&self, data: *mut u8) {
+        for column in self.columns.iter().rev() {
+            column.class.destroy(data.add(column.offset));
+        }
+    }
+
+    fn supports(&self, operation: Operation) -> bool {
+        self.columns
+            .iter()
+            .all(|column| column.class.supports(operation))
+    }
+
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        for (i, column) in self.columns.iter().enumerate() {
+            let offset = column.offset;
+            if let Err(error) = column
+                .class
+                .clone_data(source.add(offset), data.add(offset))
+            {
+                // Leave data unconstructed by destroying the columns cloned so far
+                for column in self.columns[..i].iter().rev() {
+                    column.class.destroy(data.add(column.offset));
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        for column in &self.columns {
+            let offset = column.offset;
+            if !column.class.eq_data(left.add(offset), right.add(offset))? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        for column in &self.columns {
+            column.class.hash_data(data.add(column.offset), state)?;
+        }
+        Ok(())
+    }
+}
+
+impl Unique for Columnar {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl std::fmt::Debug for Columnar {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{:?}[{}] by column",
+            self.shared.element, self.shared.length
+        )
+    }
+}
+
+unsafe impl Accessor<Lens> for Columnar {
+    fn attr(&self, name: &str) -> Result<Lens> {
+        match self.column(name) {
+            Some(column) => Ok(Lens {
+                class: column.class.clone(),
+                offset: column.offset,
+            }),
+            None => Err(Error::AttributeError(format!(
+                "Columnar {:?} has no column {}",
+                self, name
+            ))),
+        }
+    }
+
+    fn item(&self, index: usize) -> Result<Lens> {
+        self.row(index).map(|row| Lens {
+            class: row,
+            offset: 0,
+        })
+    }
+}
+
+unsafe impl Metaclass for Columnar {
+    unsafe fn construct(&self, data: *mut u8) {
+        self.shared.construct(data);
+    }
+
+    unsafe fn destroy(&self, data: *mut u8) {
+        self.shared.destroy(data);
+    }
+
+    fn supports(&self, operation: Operation) -> bool {
+        self.shared.supports(operation)
+    }
+
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        self.shared.clone_data(source, data)
+    }
+
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        self.shared.eq_data(left, right)
+    }
+
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        self.shared.hash_data(data, state)
+    }
+}
+
+unsafe impl Class for Columnar {
+    fn size(&self) -> usize {
+        self.shared.size
+    }
+
+    fn align(&self) -> usize {
+        self.shared.align
+    }
+
+    fn children(&self) -> Vec<Child> {
+        self.shared
+            .columns
+            .iter()
+            .map(|column| Child {
+                key: Key::Name(column.name.clone()),
+                class: column.class.clone(),
+                offset: column.offset,
+            })
+            .collect()
+    }
+
+    fn layout(&self) -> Layout {
+        self.shared.layout()
+    }
+}
+
+/// One item of a [`Columnar`], gathering its members from every column.
+///
+/// A row's data is the whole container rather than just the item, so that its members can be
+/// found at fixed offsets. Its size and layout are therefore those of the container: an
+/// [`Instance`](crate::instance::Instance) of a row holds, constructs and destroys every column,
+/// and code that handles classes generically, such as encoding, sees a row as the whole container.
+/// Rows are meant to be reached through a columnar container rather than instantiated on their own.
+pub struct Row {
+    id: Id,
+    shared: Arc<Shared>,
+    pub index: usize,
+}
+
+impl Row {
+    pub fn element(&self) -> &Arc<Object> {
+        &self.shared.element
+    }
+
+    /// The columns of the container this item is in.
+    pub fn columns(&self) -> &[Column] {
+        &self.shared.columns
+    }
+
+    /// The members of this item, at their offsets into the container.
+    pub fn members(&self) -> impl Iterator<Item = Child> + '_ {
+        self.shared.columns.iter().map(|column| {
+            let Lens { class, offset } = self.lens(column);
+            Child {
+                key: Key::Name(column.name.clone()),
+                class,
+                offset,
+            }
+        })
+    }
+
+    /// This item's member in column, at its offset into the container.
+    pub(crate) fn lens(&self, column: &Column) -> Lens {
+        Lens {
+            class: column.class.element.clone(),
+            offset: column.offset + column.class.element.stride() * self.index,
+        }
+    }
+}
+
+impl Unique for Row {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl std::fmt::Debug for Row {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{:?} at row {}", self.shared.element, self.index)
+    }
+}
+
+unsafe impl Accessor<Lens> for Row {
+    fn attr(&self, name: &str) -> Result<Lens> {
+        match self.shared.column(name) {
+            Some(column) => Ok(self.lens(column)),
+            None => Err(Error::AttributeError(format!(
+                "Row of {:?} has no attribute {}",
+                self.shared.element, name
+            ))),
+        }
+    }
+
+    fn item(&self, _: usize) -> Result<Lens> {
+        Err(Error::TypeError(format!(
+            "Row {:?} does not support index access!",
+            self
+        )))
+    }
+}
+
+unsafe impl Metaclass for Row {
+    unsafe fn construct(&self, data: *mut u8) {
+        self.shared.construct(data);
+    }
+
+    unsafe fn destroy(&self, data: *mut u8) {
+        self.shared.destroy(data);
+    }
+}
+
+unsafe impl Class for Row {
+    fn size(&self) -> usize {
+        self.shared.size
+    }
+
+    fn align(&self) -> usize {
+        self.shared.align
+    }
+
+    fn children(&self) -> Vec<Child> {
+        self.members().collect()
+    }
+
+    fn layout(&self) -> Layout {
+        self.shared.layout()
+    }
+}
@@ -0,0 +1,284 @@
+use crate::accessor::Accessor;
+use crate::class::id::Id;
+use crate::class::lens::Lens;
+use crate::class::pointer::Pointer;
+use crate::class::{align, Class, Metaclass, Operation, Unique};
+use crate::error::{Error, Result};
+use std::alloc::Layout;
+use std::collections::HashMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+/// The discriminant is stored at the start of every enum instance.
+type Discriminant = usize;
+
+#[derive(Clone)]
+pub struct Variant {
+    pub name: String,
+    pub class: Option<Arc<dyn Class>>,
+}
+
+pub struct Enum {
+    id: Id,
+    pub name: String,
+    variants: Vec<Variant>,
+    lookup: HashMap<String, usize>,
+    /// The first variant without a payload, if any.
+    empty: Option<usize>,
+    pub offset: usize,
+    pub size: usize,
+    align: usize,
+}
+
+impl Enum {
+    pub fn new(builder: Builder) -> Result<Self> {
+        if builder.variants.is_empty() {
+            return Err(Error::TypeError(format!(
+                "Enum {} must have at least one variant",
+                builder.name
+            )));
+        }
+
+        // Every payload shares the same storage after the discriminant
+        let payloads = builder
+            .variants
+            .iter()
+            .filter_map(|variant| variant.class.as_ref());
+        let alignment = payloads
+            .clone()
+            .map(|class| class.align())
+            .fold(align_of::<Discriminant>(), usize::max);
+        let offset = align(size_of::<Discriminant>(), alignment);
+        let size = payloads.map(|class| class.size()).max().unwrap_or(0);
+
+        Ok(Enum {
+            id: Id::new(),
+            name: builder.name,
+            empty: builder
+                .variants
+                .iter()
+                .position(|variant| variant.class.is_none()),
+            variants: builder.variants,
+            lookup: builder.lookup,
+            offset,
+            size: align(offset + size, alignment),
+            align: alignment,
+        })
+    }
+
+    /// Variants in declaration order, indexed by discriminant.
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn discriminant(&self, data: *const u8) -> usize {
+        data.cast::<Discriminant>().read()
+    }
+
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn variant(&self, data: *const u8) -> &Variant {
+        // Constructed so the discriminant is always a valid index
+        self.variants.get_unchecked(self.discriminant(data))
+    }
+
+    /// Destroy the active payload and construct the payload of the named variant in its place.
+    ///
+    /// If constructing the new payload panics, the enum is left in its first variant without a
+    /// payload, or the process aborts if it has none.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn switch(&self, data: *mut u8, name: &str) -> Result<()> {
+        let Some(&index) = self.lookup.get(name) else {
+            return Err(Error::AttributeError(format!(
+                "Enum of type {:?} has no variant {}",
+                self, name
+            )));
+        };
+
+        self.destroy(data);
+        // The old payload is gone, so the discriminant must not name it while the new one is built
+        match self.empty {
+            Some(empty) => {
+                data.cast::<Discriminant>().write(empty);
+                self.construct_variant(data, index);
+            }
+            None => {
+                let abort = Abort;
+                self.construct_variant(data, index);
+                std::mem::forget(abort);
+            }
+        }
+        Ok(())
+    }
+
+    // The discriminant is only written once its payload is constructed
+    unsafe fn construct_variant(&self, data: *mut u8, index: usize) {
+        if let Some(class) = &self.variants[index].class {
+            class.construct(data.add(self.offset));
+        }
+        data.cast::<Discriminant>().write(index);
+    }
+}
+
+/// Aborts the process when dropped, for unwinding out of a state that cannot be made valid.
+struct Abort;
+
+impl Drop for Abort {
+    fn drop(&mut self) {
+        std::process::abort();
+    }
+}
+
+impl Unique for Enum {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl std::fmt::Debug for Enum {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}", self.name)
+    }
+}
+
+unsafe impl Accessor<Lens> for Enum {
+    fn attr(&self, _: &str) -> Result<Lens> {
+        Err(Error::TypeError(format!(
+            "Enum {:?} variants can only be accessed through an instance!",
+            self
+        )))
+    }
+
+    fn item(&self, _: usize) -> Result<Lens> {
+        Err(Error::TypeError(format!(
+            "Enum of type {:?} does not support index access!",
+            self
+        )))
+    }
+}
+
+unsafe impl Metaclass for Enum {
+    unsafe fn construct(&self, data: *mut u8) {
+        self.construct_variant(data, 0);
+    }
+
+    unsafe fn destroy(&self, data: *mut u8) {
+        if let Some(class) = &self.variant(data).class {
+            class.destroy(data.add(self.offset));
+        }
+    }
+
+    fn supports(&self, operation: Operation) -> bool {
+        self.variants
+            .iter()
+            .filter_map(|variant| variant.class.as_ref())
+            .all(|class| class.supports(operation))
+    }
+
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        let index = self.discriminant(source);
+        if let Some(class) = &self.variants[index].class {
+            class.clone_data(source.add(self.offset), data.add(self.offset))?;
+        }
+        data.cast::<Discriminant>().write(index);
+        Ok(())
+    }
+
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        let index = self.discriminant(left);
+        if index != self.discriminant(right) {
+            return Ok(false);
+        }
+        match &self.variants[index].class {
+            Some(class) => class.eq_data(left.add(self.offset), right.add(self.offset)),
+            None => Ok(true),
+        }
+    }
+
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        let variant = self.variant(data);
+        state.write_usize(self.discriminant(data));
+        if let Some(class) = &variant.class {
+            class.hash_data(data.add(self.offset), state)?;
+        }
+        Ok(())
+    }
+}
+
+unsafe impl Class for Enum {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn align(&self) -> usize {
+        self.align
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::from_size_align(self.size, self.align).unwrap()
+    }
+
+    unsafe fn attr_at(&self, data: *mut u8, name: &str) -> Result<Pointer> {
+        let variant = self.variant(data);
+        if variant.name == name {
+            match &variant.class {
+                Some(class) => Ok(Pointer {
+                    class: class.clone(),
+                    data: data.add(self.offset),
+                }),
+                None => Err(Error::ValueError(format!(
+                    "Enum {:?} variant {} has no payload",
+                    self, name
+                ))),
+            }
+        } else if self.lookup.contains_key(name) {
+            Err(Error::ValueError(format!(
+                "Enum {:?} variant {} is not active, {} is",
+                self, name, variant.name
+            )))
+        } else {
+            Err(Error::AttributeError(format!(
+                "Enum of type {:?} has no variant {}",
+                self, name
+            )))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Builder {
+    pub name: String,
+    variants: Vec<Variant>,
+    lookup: HashMap<String, usize>,
+}
+
+impl Builder {
+    pub fn new(name: String) -> Self {
+        Builder {
+            name,
+            variants: Vec::new(),
+            lookup: HashMap::new(),
+        }
+    }
+
+    /// The first variant added is the one instances are constructed with.
+    pub fn add(&mut self, name: String, class: Option<Arc<dyn Class>>) -> Result<()> {
+        if self.lookup.contains_key(&name) {
+            return Err(Error::AttributeError(format!(
+                "Enum {} already has a variant {}",
+                self.name, name
+            )));
+        }
+
+        self.lookup.insert(name.clone(), self.variants.len());
+        self.variants.push(Variant { name, class });
+        Ok(())
+    }
+}
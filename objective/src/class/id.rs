@@ -13,4 +13,15 @@ impl Id {
             value: AUTOINCREMENT.fetch_add(1, Ordering::Relaxed),
         }
     }
+
+    /// Never zero and never reused by another class.
+    pub(crate) fn value(&self) -> usize {
+        self.value
+    }
+}
+
+impl Default for Id {
+    fn default() -> Self {
+        Self::new()
+    }
 }
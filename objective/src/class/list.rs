@@ -0,0 +1,324 @@
+use crate::accessor::Accessor;
+use crate::class::id::Id;
+use crate::class::lens::Lens;
+use crate::class::pointer::Pointer;
+use crate::class::{Class, Metaclass, Operation, Unique};
+use crate::error::{Error, Result};
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::hash::Hasher;
+use std::sync::Arc;
+
+/// What a list instance stores inline; elements live in a separate allocation from the global
+/// allocator, whichever allocator the instance itself came from.
+#[repr(C)]
+struct Buffer {
+    data: *mut u8,
+    length: usize,
+    capacity: usize,
+}
+
+pub struct List {
+    id: Id,
+    pub element: Arc<dyn Class>,
+}
+
+impl List {
+    pub fn new(element: Arc<dyn Class>) -> Self {
+        Self {
+            id: Id::new(),
+            element,
+        }
+    }
+
+    fn buffer_layout(&self, capacity: usize) -> Layout {
+        Layout::from_size_align(self.element.stride() * capacity, self.element.align()).unwrap()
+    }
+
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn len(&self, data: *const u8) -> usize {
+        (*data.cast::<Buffer>()).length
+    }
+
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn is_empty(&self, data: *const u8) -> bool {
+        self.len(data) == 0
+    }
+
+    /// The address of the first element, which the others follow at the element's stride.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub(crate) unsafe fn elements(&self, data: *const u8) -> *mut u8 {
+        (*data.cast::<Buffer>()).data
+    }
+
+    // Invariant: after returning Ok, capacity >= length + 1
+    unsafe fn grow(&self, buffer: &mut Buffer) -> Result<()> {
+        if buffer.length < buffer.capacity {
+            return Ok(());
+        }
+
+        let length = buffer.length;
+        let full = || {
+            Error::IndexError(format!(
+                "List {:?} cannot grow beyond {} elements",
+                self, length
+            ))
+        };
+        let stride = self.element.stride();
+        if stride == 0 {
+            // Zero-sized elements never need backing memory
+            if buffer.capacity == usize::MAX {
+                return Err(full());
+            }
+            buffer.capacity = usize::MAX;
+            return Ok(());
+        }
+
+        let capacity = usize::max(4, buffer.capacity.checked_mul(2).ok_or_else(full)?);
+        let layout = stride
+            .checked_mul(capacity)
+            .and_then(|size| Layout::from_size_align(size, self.element.align()).ok())
+            .ok_or_else(full)?;
+        let data = alloc(layout);
+        if data.is_null() {
+            handle_alloc_error(layout);
+        }
+
+        // Elements are relocated bytewise, the same way Rust moves values
+        if buffer.capacity != 0 {
+            data.copy_from_nonoverlapping(buffer.data, stride * buffer.length);
+            dealloc(buffer.data, self.buffer_layout(buffer.capacity));
+        }
+        buffer.data = data;
+        buffer.capacity = capacity;
+        Ok(())
+    }
+
+    /// Construct a new element at the end of the list and return its address.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn push(&self, data: *mut u8) -> Result<*mut u8> {
+        let length = self.len(data);
+        self.insert(data, length)
+    }
+
+    /// Destroy the last element of the list.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn pop(&self, data: *mut u8) -> Result<()> {
+        match self.len(data) {
+            0 => Err(Error::IndexError(format!("Pop from empty list {:?}", self))),
+            length => self.remove(data, length - 1),
+        }
+    }
+
+    /// Construct a new element before index and return its address.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn insert(&self, data: *mut u8, index: usize) -> Result<*mut u8> {
+        let buffer = &mut *data.cast::<Buffer>();
+        if index > buffer.length {
+            return Err(Error::IndexError(format!(
+                "List insert index {} out of bounds {}",
+                index, buffer.length
+            )));
+        }
+
+        self.grow(buffer)?;
+        let stride = self.element.stride();
+        let address = buffer.data.add(stride * index);
+        let shifted = Shifted {
+            address,
+            stride,
+            count: buffer.length - index,
+        };
+        address.copy_to(address.add(stride), stride * shifted.count);
+        self.element.construct(address);
+        std::mem::forget(shifted);
+        buffer.length += 1;
+        Ok(address)
+    }
+
+    /// Destroy the element at index, shifting subsequent elements down.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn remove(&self, data: *mut u8, index: usize) -> Result<()> {
+        let buffer = &mut *data.cast::<Buffer>();
+        if index >= buffer.length {
+            return Err(Error::IndexError(format!(
+                "List index {} out of bounds {}",
+                index, buffer.length
+            )));
+        }
+
+        let stride = self.element.stride();
+        let address = buffer.data.add(stride * index);
+        self.element.destroy(address);
+        address.copy_from(address.add(stride), stride * (buffer.length - index - 1));
+        buffer.length -= 1;
+        Ok(())
+    }
+}
+
+/// Elements moved up to make room at address, which are moved back down if constructing the new
+/// element there unwinds, so that the list never holds a copy of an element it also still owns.
+struct Shifted {
+    address: *mut u8,
+    stride: usize,
+    count: usize,
+}
+
+impl Drop for Shifted {
+    fn drop(&mut self) {
+        // Invariant: count elements were moved from address to one stride above it
+        unsafe {
+            self.address
+                .copy_from(self.address.add(self.stride), self.stride * self.count)
+        };
+    }
+}
+
+impl Unique for List {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl std::fmt::Debug for List {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{:?}[]", self.element)
+    }
+}
+
+unsafe impl Accessor<Lens> for List {
+    fn attr(&self, _: &str) -> Result<Lens> {
+        Err(Error::TypeError(format!(
+            "List {:?} does not support attribute access!",
+            self
+        )))
+    }
+
+    fn item(&self, _: usize) -> Result<Lens> {
+        Err(Error::TypeError(format!(
+            "List {:?} elements can only be accessed through an instance!",
+            self
+        )))
+    }
+}
+
+unsafe impl Metaclass for List {
+    unsafe fn construct(&self, data: *mut u8) {
+        data.cast::<Buffer>().write(Buffer {
+            data: self.element.align() as *mut u8,
+            length: 0,
+            capacity: 0,
+        });
+    }
+
+    unsafe fn destroy(&self, data: *mut u8) {
+        let buffer = &mut *data.cast::<Buffer>();
+        let stride = self.element.stride();
+        for i in (0..buffer.length).rev() {
+            self.element.destroy(buffer.data.add(stride * i));
+        }
+        if stride != 0 && buffer.capacity != 0 {
+            dealloc(buffer.data, self.buffer_layout(buffer.capacity));
+        }
+    }
+
+    fn supports(&self, operation: Operation) -> bool {
+        self.element.supports(operation)
+    }
+
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        let source = &*source.cast::<Buffer>();
+        self.construct(data);
+        let buffer = &mut *data.cast::<Buffer>();
+        let stride = self.element.stride();
+        for i in 0..source.length {
+            let result = self.grow(buffer).and_then(|()| {
+                self.element
+                    .clone_data(source.data.add(stride * i), buffer.data.add(stride * i))
+            });
+            if let Err(error) = result {
+                // Leave data unconstructed by destroying the elements cloned so far
+                self.destroy(data);
+                return Err(error);
+            }
+            buffer.length += 1;
+        }
+        Ok(())
+    }
+
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        let (left, right) = (&*left.cast::<Buffer>(), &*right.cast::<Buffer>());
+        if left.length != right.length {
+            return Ok(false);
+        }
+        let stride = self.element.stride();
+        for i in 0..left.length {
+            let offset = stride * i;
+            if !self
+                .element
+                .eq_data(left.data.add(offset), right.data.add(offset))?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // The length goes first so that lists split differently hash differently
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        let buffer = &*data.cast::<Buffer>();
+        state.write_usize(buffer.length);
+        let stride = self.element.stride();
+        for i in 0..buffer.length {
+            self.element.hash_data(buffer.data.add(stride * i), state)?;
+        }
+        Ok(())
+    }
+}
+
+unsafe impl Class for List {
+    fn size(&self) -> usize {
+        size_of::<Buffer>()
+    }
+
+    fn align(&self) -> usize {
+        align_of::<Buffer>()
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::new::<Buffer>()
+    }
+
+    unsafe fn item_at(&self, data: *mut u8, index: usize) -> Result<Pointer> {
+        let buffer = &*data.cast::<Buffer>();
+        if index < buffer.length {
+            Ok(Pointer {
+                class: self.element.clone(),
+                data: buffer.data.add(self.element.stride() * index),
+            })
+        } else {
+            Err(Error::IndexError(format!(
+                "List index {} out of bounds {}",
+                index, buffer.length
+            )))
+        }
+    }
+}
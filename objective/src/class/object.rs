@@ -1,10 +1,14 @@
 use crate::accessor::Accessor;
+use crate::class::child::{Child, Key};
 use crate::class::id::Id;
 use crate::class::lens::Lens;
-use crate::class::{Class, Metaclass, Unique};
+use crate::class::value::Value;
+use crate::class::{align, place, Class, Metaclass, Operation, Unique};
 use crate::error::{Error, Result};
+use crate::native::Objective;
 use std::alloc::Layout;
 use std::collections::HashMap;
+use std::hash::Hasher;
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -14,6 +18,35 @@ pub struct Member {
     pub offset: usize,
 }
 
+impl Member {
+    pub fn size(&self) -> usize {
+        self.class.size()
+    }
+
+    pub fn align(&self) -> usize {
+        self.class.align()
+    }
+}
+
+/// The alignment of an object with these members, the largest of theirs.
+fn members_align(members: &[Member]) -> usize {
+    members
+        .iter()
+        .map(|member| member.class.align())
+        .max()
+        .unwrap_or(1)
+}
+
+impl std::fmt::Debug for Member {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{}: {:?} @ {}",
+            self.name, self.class, self.offset
+        )
+    }
+}
+
 pub struct Object {
     id: Id,
     pub name: String,
@@ -25,15 +58,29 @@ pub struct Object {
 
 impl Object {
     pub fn new(builder: Builder) -> Self {
+        // Trailing padding so that arrays of this object keep every element aligned
+        let size = align(builder.size, builder.align());
         Object {
             id: Id::new(),
             name: builder.name,
             base: builder.base,
             members: builder.members,
             lookup: builder.lookup,
-            size: builder.size,
+            size,
         }
     }
+
+    /// Members in declaration order, including those inherited from the base.
+    pub fn members(&self) -> &[Member] {
+        &self.members
+    }
+
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        // Constructed so every key is always a valid index, immutable.
+        self.lookup
+            .get(name)
+            .map(|index| unsafe { self.members.get_unchecked(*index) })
+    }
 }
 
 impl Unique for Object {
@@ -93,6 +140,45 @@ unsafe impl Metaclass for Object {
             }
         }
     }
+
+    fn supports(&self, operation: Operation) -> bool {
+        self.members
+            .iter()
+            .all(|member| member.class.supports(operation))
+    }
+
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        for (i, member) in self.members.iter().enumerate() {
+            let address = data.add(member.offset);
+            if let Err(error) = member.class.clone_data(source.add(member.offset), address) {
+                // Leave data unconstructed by destroying the members cloned so far
+                for member in self.members[..i].iter().rev() {
+                    member.class.destroy(data.add(member.offset));
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        for member in self.members.iter() {
+            if !member
+                .class
+                .eq_data(left.add(member.offset), right.add(member.offset))?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        for member in self.members.iter() {
+            member.class.hash_data(data.add(member.offset), state)?;
+        }
+        Ok(())
+    }
 }
 
 unsafe impl Class for Object {
@@ -101,15 +187,22 @@ unsafe impl Class for Object {
     }
 
     fn align(&self) -> usize {
-        if self.members.len() == 0 {
-            1
-        } else {
-            self.members
-                .iter()
-                .map(|member| member.class.align())
-                .max()
-                .unwrap_or(1)
-        }
+        members_align(&self.members)
+    }
+
+    fn base(&self) -> Option<&Arc<dyn Class>> {
+        self.base.as_ref()
+    }
+
+    fn children(&self) -> Vec<Child> {
+        self.members
+            .iter()
+            .map(|member| Child {
+                key: Key::Name(member.name.clone()),
+                class: member.class.clone(),
+                offset: member.offset,
+            })
+            .collect()
     }
 
     fn layout(&self) -> Layout {
@@ -128,14 +221,6 @@ pub struct Builder {
     pub size: usize,
 }
 
-fn align(offset: usize, align: usize) -> usize {
-    if align == 0 {
-        offset
-    } else {
-        (offset + align - 1) / align * align
-    }
-}
-
 impl Builder {
     pub fn new(name: String) -> Self {
         Builder {
@@ -157,14 +242,36 @@ impl Builder {
         }
     }
 
-    pub fn add(&mut self, name: String, class: Arc<dyn Class>) {
-        let offset = align(self.size, class.align());
-        self.size = offset + class.size();
+    pub fn align(&self) -> usize {
+        members_align(&self.members)
+    }
+
+    pub fn add(&mut self, name: String, class: Arc<dyn Class>) -> Result<()> {
+        if self.lookup.contains_key(&name) {
+            return Err(Error::AttributeError(format!(
+                "Object {} already has an attribute {}",
+                self.name, name
+            )));
+        }
+
+        let (offset, size) = place(self.size, self.align(), &*class)
+            .ok_or_else(|| Error::TypeError(format!("Object {} is too large", self.name)))?;
+        self.size = size;
         self.lookup.insert(name.clone(), self.members.len());
         self.members.push(Member {
             name,
             class,
             offset,
         });
+        Ok(())
+    }
+
+    /// Add a member of a native value type whose instances start as a copy of value rather than
+    /// the type's default.
+    pub fn add_with_default<T>(&mut self, name: String, value: T) -> Result<()>
+    where
+        T: Objective<Class = Value<T>> + Clone + Send + Sync,
+    {
+        self.add(name, Arc::new(T::class().with_default(value)))
     }
 }
@@ -0,0 +1,173 @@
+use crate::accessor::Accessor;
+use crate::class::id::Id;
+use crate::class::lens::Lens;
+use crate::class::pointer::Pointer;
+use crate::class::{align, Class, Metaclass, Operation, Unique};
+use crate::error::{Error, Result};
+use std::alloc::Layout;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+/// The presence flag is stored at the start of every optional instance.
+type Flag = bool;
+
+pub struct Optional {
+    id: Id,
+    pub inner: Arc<dyn Class>,
+    pub offset: usize,
+    pub size: usize,
+}
+
+impl Optional {
+    pub fn new(inner: Arc<dyn Class>) -> Self {
+        let offset = align(size_of::<Flag>(), inner.align());
+        let size = align(offset + inner.size(), inner.align());
+        Self {
+            id: Id::new(),
+            inner,
+            offset,
+            size,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn is_set(&self, data: *const u8) -> bool {
+        data.cast::<Flag>().read()
+    }
+
+    /// Construct the inner value if it is not already present and return its address.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn set(&self, data: *mut u8) -> *mut u8 {
+        let address = data.add(self.offset);
+        if !self.is_set(data) {
+            self.inner.construct(address);
+            data.cast::<Flag>().write(true);
+        }
+        address
+    }
+
+    /// Destroy the inner value if it is present.
+    ///
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn clear(&self, data: *mut u8) {
+        if self.is_set(data) {
+            data.cast::<Flag>().write(false);
+            self.inner.destroy(data.add(self.offset));
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `data` must have been constructed by this class.
+    pub unsafe fn get(&self, data: *mut u8) -> Result<Pointer> {
+        if self.is_set(data) {
+            Ok(Pointer {
+                class: self.inner.clone(),
+                data: data.add(self.offset),
+            })
+        } else {
+            Err(Error::ValueError(format!("Optional {:?} is empty!", self)))
+        }
+    }
+}
+
+impl Unique for Optional {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl std::fmt::Debug for Optional {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{:?}?", self.inner)
+    }
+}
+
+unsafe impl Accessor<Lens> for Optional {
+    fn attr(&self, _: &str) -> Result<Lens> {
+        Err(Error::TypeError(format!(
+            "Optional {:?} can only be accessed through an instance!",
+            self
+        )))
+    }
+
+    fn item(&self, _: usize) -> Result<Lens> {
+        Err(Error::TypeError(format!(
+            "Optional {:?} can only be accessed through an instance!",
+            self
+        )))
+    }
+}
+
+unsafe impl Metaclass for Optional {
+    unsafe fn construct(&self, data: *mut u8) {
+        data.cast::<Flag>().write(false);
+    }
+
+    unsafe fn destroy(&self, data: *mut u8) {
+        self.clear(data);
+    }
+
+    fn supports(&self, operation: Operation) -> bool {
+        self.inner.supports(operation)
+    }
+
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        self.construct(data);
+        if self.is_set(source) {
+            self.inner
+                .clone_data(source.add(self.offset), data.add(self.offset))?;
+            data.cast::<Flag>().write(true);
+        }
+        Ok(())
+    }
+
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        match (self.is_set(left), self.is_set(right)) {
+            (true, true) => self
+                .inner
+                .eq_data(left.add(self.offset), right.add(self.offset)),
+            (left, right) => Ok(left == right),
+        }
+    }
+
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        let set = self.is_set(data);
+        state.write_u8(set as u8);
+        if set {
+            self.inner.hash_data(data.add(self.offset), state)?;
+        }
+        Ok(())
+    }
+}
+
+unsafe impl Class for Optional {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn align(&self) -> usize {
+        usize::max(align_of::<Flag>(), self.inner.align())
+    }
+
+    fn layout(&self) -> Layout {
+        Layout::from_size_align(self.size, self.align()).unwrap()
+    }
+
+    unsafe fn attr_at(&self, data: *mut u8, name: &str) -> Result<Pointer> {
+        let inner = self.get(data)?;
+        inner.class.attr_at(inner.data, name)
+    }
+
+    unsafe fn item_at(&self, data: *mut u8, index: usize) -> Result<Pointer> {
+        let inner = self.get(data)?;
+        inner.class.item_at(inner.data, index)
+    }
+}
@@ -0,0 +1,7 @@
+use crate::class::Class;
+use std::sync::Arc;
+
+pub struct Pointer {
+    pub class: Arc<dyn Class>,
+    pub data: *mut u8,
+}
@@ -0,0 +1,68 @@
+use crate::class::array::Array;
+use crate::class::enumeration::Enum;
+use crate::class::list::List;
+use crate::class::object::Object;
+use crate::class::optional::Optional;
+use crate::class::Class;
+
+/// Compare two classes member by member, ignoring their names and identities.
+pub(crate) fn identical(a: &dyn Class, b: &dyn Class) -> bool {
+    if a.id() == b.id() {
+        return true;
+    }
+    if a.size() != b.size() || a.align() != b.align() {
+        return false;
+    }
+
+    if let (Some(a), Some(b)) = (a.value(), b.value()) {
+        a == b
+    } else if let (Some(a), Some(b)) = (a.downcast_ref::<Object>(), b.downcast_ref::<Object>()) {
+        a.members().len() == b.members().len()
+            && a.members().iter().zip(b.members()).all(|(a, b)| {
+                a.name == b.name && a.offset == b.offset && identical(&*a.class, &*b.class)
+            })
+    } else if let (Some(a), Some(b)) = (a.downcast_ref::<Array>(), b.downcast_ref::<Array>()) {
+        a.length == b.length && identical(&*a.element, &*b.element)
+    } else if let (Some(a), Some(b)) = (a.downcast_ref::<List>(), b.downcast_ref::<List>()) {
+        identical(&*a.element, &*b.element)
+    } else if let (Some(a), Some(b)) = (a.downcast_ref::<Optional>(), b.downcast_ref::<Optional>())
+    {
+        identical(&*a.inner, &*b.inner)
+    } else if let (Some(a), Some(b)) = (a.downcast_ref::<Enum>(), b.downcast_ref::<Enum>()) {
+        a.variants().len() == b.variants().len()
+            && a.variants().iter().zip(b.variants()).all(|(a, b)| {
+                a.name == b.name
+                    && match (&a.class, &b.class) {
+                        (Some(a), Some(b)) => identical(&**a, &**b),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            })
+    } else {
+        false
+    }
+}
+
+/// Whether the leading part of class is laid out exactly like prefix.
+pub(crate) fn prefix(prefix: &dyn Class, class: &dyn Class) -> bool {
+    if identical(prefix, class) {
+        return true;
+    }
+
+    if let (Some(prefix), Some(class)) = (
+        prefix.downcast_ref::<Object>(),
+        class.downcast_ref::<Object>(),
+    ) {
+        prefix.members().len() <= class.members().len()
+            && prefix.members().iter().zip(class.members()).all(|(a, b)| {
+                a.name == b.name && a.offset == b.offset && identical(&*a.class, &*b.class)
+            })
+    } else if let (Some(prefix), Some(class)) = (
+        prefix.downcast_ref::<Array>(),
+        class.downcast_ref::<Array>(),
+    ) {
+        prefix.length <= class.length && identical(&*prefix.element, &*class.element)
+    } else {
+        false
+    }
+}
@@ -1,25 +1,106 @@
 use crate::accessor::Accessor;
 use crate::class::id::Id;
 use crate::class::lens::Lens;
-use crate::class::{Class, Metaclass, Unique};
+use crate::class::{Class, Metaclass, Operation, Unique};
 use crate::error::{Error, Result};
 use std::alloc::Layout;
 use std::any::{type_name, TypeId};
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use std::mem::{align_of, size_of};
+use std::sync::Arc;
 
-#[derive(Eq, PartialEq)]
 pub struct Value<T> {
     id: Id,
-    phantom_data: PhantomData<T>,
+    initializer: Arc<dyn Fn() -> T + Send + Sync>,
+    clone: Option<unsafe fn(*const u8, *mut u8)>,
+    eq: Option<unsafe fn(*const u8, *const u8) -> bool>,
+    hash: Option<unsafe fn(*const u8, &mut dyn Hasher)>,
+    phantom_data: PhantomData<fn() -> T>,
 }
 
-impl<T: 'static> Value<T> {
+impl<T: Default + 'static> Value<T> {
+    /// A value class without any optional operations; enable them with the `with_*` methods.
     pub fn new() -> Self {
-        return Value {
+        Self::from_fn(T::default)
+    }
+}
+
+impl<T: 'static> Value<T> {
+    /// A value class whose instances are constructed by calling initializer, which lets types
+    /// without a `Default` be stored.
+    pub fn from_fn(initializer: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        Value {
+            id: Id::new(),
+            initializer: Arc::new(initializer),
+            clone: None,
+            eq: None,
+            hash: None,
+            phantom_data: Default::default(),
+        }
+    }
+
+    /// A new class with the same operations as this one whose instances are constructed by
+    /// calling initializer.
+    pub fn with_initializer(&self, initializer: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        Value {
             id: Id::new(),
+            initializer: Arc::new(initializer),
+            clone: self.clone,
+            eq: self.eq,
+            hash: self.hash,
             phantom_data: Default::default(),
-        };
+        }
+    }
+
+    /// A new class with the same operations as this one whose instances start as a copy of value.
+    pub fn with_default(&self, value: T) -> Self
+    where
+        T: Clone + Send + Sync,
+    {
+        self.with_initializer(move || value.clone())
+    }
+}
+
+impl<T: Clone + 'static> Value<T> {
+    pub fn with_clone(self) -> Self {
+        unsafe fn clone<T: Clone>(source: *const u8, data: *mut u8) {
+            data.cast::<T>().write((*source.cast::<T>()).clone());
+        }
+        Value {
+            clone: Some(clone::<T>),
+            ..self
+        }
+    }
+}
+
+impl<T: PartialEq + 'static> Value<T> {
+    pub fn with_eq(self) -> Self {
+        unsafe fn eq<T: PartialEq>(left: *const u8, right: *const u8) -> bool {
+            *left.cast::<T>() == *right.cast::<T>()
+        }
+        Value {
+            eq: Some(eq::<T>),
+            ..self
+        }
+    }
+}
+
+impl<T: Hash + 'static> Value<T> {
+    pub fn with_hash(self) -> Self {
+        unsafe fn hash<T: Hash>(data: *const u8, mut state: &mut dyn Hasher) {
+            (*data.cast::<T>()).hash(&mut state);
+        }
+        Value {
+            hash: Some(hash::<T>),
+            ..self
+        }
+    }
+}
+
+impl<T: Default + 'static> Default for Value<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -51,19 +132,44 @@ unsafe impl<T> Accessor<Lens> for Value<T> {
     }
 }
 
-unsafe impl<T: Default> Metaclass for Value<T> {
+unsafe impl<T> Metaclass for Value<T> {
     unsafe fn construct(&self, data: *mut u8) {
-        data.cast::<T>().write(T::default());
+        data.cast::<T>().write((self.initializer)());
     }
 
     unsafe fn destroy(&self, data: *mut u8) {
         data.cast::<T>().drop_in_place();
     }
+
+    fn supports(&self, operation: Operation) -> bool {
+        match operation {
+            Operation::Clone => self.clone.is_some(),
+            Operation::Eq => self.eq.is_some(),
+            Operation::Hash => self.hash.is_some(),
+        }
+    }
+
+    unsafe fn clone_data(&self, source: *const u8, data: *mut u8) -> Result<()> {
+        let clone = self.clone.ok_or_else(|| Operation::Clone.unsupported())?;
+        clone(source, data);
+        Ok(())
+    }
+
+    unsafe fn eq_data(&self, left: *const u8, right: *const u8) -> Result<bool> {
+        let eq = self.eq.ok_or_else(|| Operation::Eq.unsupported())?;
+        Ok(eq(left, right))
+    }
+
+    unsafe fn hash_data(&self, data: *const u8, state: &mut dyn Hasher) -> Result<()> {
+        let hash = self.hash.ok_or_else(|| Operation::Hash.unsupported())?;
+        hash(data, state);
+        Ok(())
+    }
 }
 
 unsafe impl<T> Class for Value<T>
 where
-    T: Sized + Default + 'static,
+    T: Sized + 'static,
 {
     fn size(&self) -> usize {
         size_of::<T>()
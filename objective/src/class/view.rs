@@ -1,7 +1,12 @@
 use crate::accessor::{Accessor, IntoAccessor};
 use crate::class::lens::Lens;
 use crate::class::Class;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::instance::read::InstanceReadGuard;
+use crate::instance::write::InstanceWriteGuard;
+use std::any::{type_name, TypeId};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -27,6 +32,24 @@ impl View {
             offset: self.offset + lens.offset,
         }
     }
+
+    /// Compile this view into a flat offset to a value of type U for repeated access.
+    pub fn typed<U: 'static>(&self) -> Result<TypedView<U>> {
+        if self.class.value() == Some(TypeId::of::<U>()) {
+            Ok(TypedView {
+                compatible: AtomicUsize::new(self.origin.id().value()),
+                origin: self.origin.clone(),
+                offset: self.offset,
+                phantom_data: PhantomData,
+            })
+        } else {
+            Err(Error::ValueError(format!(
+                "Cannot view {:?} as {}!",
+                self.class,
+                type_name::<U>(),
+            )))
+        }
+    }
 }
 
 unsafe impl IntoAccessor<View> for View {
@@ -68,3 +91,81 @@ unsafe impl<T: Class + 'static> Accessor<View> for Arc<T> {
         View::of(self.clone()).item(index)
     }
 }
+
+/// A view whose target type was checked when it was compiled, so that accessing an instance only
+/// costs an identity comparison and a pointer offset.
+///
+/// Instances of other classes are checked for compatibility once, after which the view remembers
+/// the last compatible class by id.
+pub struct TypedView<T> {
+    origin: Arc<dyn Class>,
+    offset: usize,
+    /// Id of the last class found compatible with origin.
+    compatible: AtomicUsize,
+    phantom_data: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for TypedView<T> {
+    fn clone(&self) -> Self {
+        TypedView {
+            origin: self.origin.clone(),
+            offset: self.offset,
+            compatible: AtomicUsize::new(self.compatible.load(Ordering::Relaxed)),
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static> TypedView<T> {
+    pub fn origin(&self) -> &Arc<dyn Class> {
+        &self.origin
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // Ids are never reused, so a class once found compatible stays compatible
+    fn check(&self, class: &dyn Class) {
+        let id = class.id().value();
+        if self.compatible.load(Ordering::Relaxed) == id {
+            return;
+        }
+        assert!(
+            class.is_compatible_with(&*self.origin),
+            "View of type {:?} cannot be applied to instance of type {:?}",
+            self.origin,
+            class
+        );
+        self.compatible.store(id, Ordering::Relaxed);
+    }
+
+    /// Panics if the instance is not compatible with the class this view was compiled against.
+    pub fn get<'a>(&self, instance: &'a InstanceReadGuard<'_>) -> &'a T {
+        self.check(&**instance.class());
+        unsafe { self.get_unchecked(instance) }
+    }
+
+    /// # Safety
+    ///
+    /// The instance must be compatible with the class this view was compiled against.
+    pub unsafe fn get_unchecked<'a>(&self, instance: &'a InstanceReadGuard<'_>) -> &'a T {
+        &*instance.data().add(self.offset).cast::<T>()
+    }
+
+    /// Panics if the instance is not compatible with the class this view was compiled against.
+    pub fn get_mut<'a>(&self, instance: &'a mut InstanceWriteGuard<'_>) -> &'a mut T {
+        self.check(&**instance.class());
+        unsafe { self.get_mut_unchecked(instance) }
+    }
+
+    /// # Safety
+    ///
+    /// The instance must be compatible with the class this view was compiled against.
+    pub unsafe fn get_mut_unchecked<'a>(
+        &self,
+        instance: &'a mut InstanceWriteGuard<'_>,
+    ) -> &'a mut T {
+        &mut *instance.data().add(self.offset).cast::<T>()
+    }
+}
@@ -0,0 +1,260 @@
+use crate::class::array::Array;
+use crate::class::enumeration::Enum;
+use crate::class::list::List;
+use crate::class::object::Object;
+use crate::class::optional::Optional;
+use crate::class::Class;
+use crate::error::{Error, Result};
+use std::any::TypeId;
+
+/// A self-describing copy of instance data for tooling that does not know the Rust types involved.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    List(Vec<DynValue>),
+    Map(Vec<(String, DynValue)>),
+}
+
+impl DynValue {
+    fn kind(&self) -> &'static str {
+        match self {
+            DynValue::Null => "null",
+            DynValue::Bool(_) => "bool",
+            DynValue::Int(_) => "int",
+            DynValue::UInt(_) => "uint",
+            DynValue::Float(_) => "float",
+            DynValue::String(_) => "string",
+            DynValue::List(_) => "list",
+            DynValue::Map(_) => "map",
+        }
+    }
+}
+
+macro_rules! load_primitives {
+    ($type_id:ident, $data:ident, $($type:ty => $variant:ident),*) => {
+        $(
+            if $type_id == TypeId::of::<$type>() {
+                return Ok(DynValue::$variant((*$data.cast::<$type>()).into()));
+            }
+        )*
+    };
+}
+
+macro_rules! store_integers {
+    ($type_id:ident, $data:ident, $value:ident, $($type:ty),*) => {
+        $(
+            if $type_id == TypeId::of::<$type>() {
+                let converted = match $value {
+                    DynValue::Int(value) => <$type>::try_from(*value).ok(),
+                    DynValue::UInt(value) => <$type>::try_from(*value).ok(),
+                    _ => return Err(mismatch($value, stringify!($type))),
+                };
+                let Some(converted) = converted else {
+                    return Err(Error::ValueError(format!(
+                        "{:?} does not fit in {}!",
+                        $value,
+                        stringify!($type)
+                    )));
+                };
+                *$data.cast::<$type>() = converted;
+                return Ok(());
+            }
+        )*
+    };
+}
+
+fn mismatch(value: &DynValue, expected: &str) -> Error {
+    Error::TypeError(format!(
+        "Cannot store {} value in {}!",
+        value.kind(),
+        expected
+    ))
+}
+
+/// Recursively copy constructed data into a dynamic value.
+///
+/// # Safety
+///
+/// `data` must have been constructed by class.
+pub(crate) unsafe fn load(class: &dyn Class, data: *mut u8) -> Result<DynValue> {
+    if let Some(type_id) = class.value() {
+        load_primitives!(
+            type_id, data,
+            bool => Bool,
+            i8 => Int, i16 => Int, i32 => Int, i64 => Int,
+            u8 => UInt, u16 => UInt, u32 => UInt, u64 => UInt,
+            f32 => Float, f64 => Float
+        );
+        if type_id == TypeId::of::<isize>() {
+            return Ok(DynValue::Int(*data.cast::<isize>() as i64));
+        } else if type_id == TypeId::of::<usize>() {
+            return Ok(DynValue::UInt(*data.cast::<usize>() as u64));
+        } else if type_id == TypeId::of::<String>() {
+            return Ok(DynValue::String((*data.cast::<String>()).clone()));
+        }
+        Err(Error::TypeError(format!(
+            "Value class {:?} has no dynamic representation!",
+            class
+        )))
+    } else if let Some(object) = class.downcast_ref::<Object>() {
+        object
+            .members()
+            .iter()
+            .map(|member| {
+                Ok((
+                    member.name.clone(),
+                    load(&*member.class, data.add(member.offset))?,
+                ))
+            })
+            .collect::<Result<_>>()
+            .map(DynValue::Map)
+    } else if let Some(array) = class.downcast_ref::<Array>() {
+        (0..array.length)
+            .map(|i| {
+                let pointer = array.item_at(data, i)?;
+                load(&*pointer.class, pointer.data)
+            })
+            .collect::<Result<_>>()
+            .map(DynValue::List)
+    } else if let Some(list) = class.downcast_ref::<List>() {
+        (0..list.len(data))
+            .map(|i| {
+                let pointer = list.item_at(data, i)?;
+                load(&*pointer.class, pointer.data)
+            })
+            .collect::<Result<_>>()
+            .map(DynValue::List)
+    } else if let Some(optional) = class.downcast_ref::<Optional>() {
+        match optional.is_set(data) {
+            true => load(&*optional.inner, data.add(optional.offset)),
+            false => Ok(DynValue::Null),
+        }
+    } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+        let variant = enumeration.variant(data);
+        let payload = match &variant.class {
+            Some(class) => load(&**class, data.add(enumeration.offset))?,
+            None => DynValue::Null,
+        };
+        Ok(DynValue::Map(vec![(variant.name.clone(), payload)]))
+    } else {
+        Err(Error::TypeError(format!(
+            "Class {:?} has no dynamic representation!",
+            class
+        )))
+    }
+}
+
+/// Recursively overwrite constructed data from a dynamic value. Object members missing from a map
+/// are left untouched. On error, members visited before the failure keep their new values.
+///
+/// # Safety
+///
+/// `data` must have been constructed by class.
+pub(crate) unsafe fn store(class: &dyn Class, data: *mut u8, value: &DynValue) -> Result<()> {
+    if let Some(type_id) = class.value() {
+        store_integers!(type_id, data, value, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+        if type_id == TypeId::of::<bool>() {
+            *data.cast::<bool>() = match value {
+                DynValue::Bool(value) => *value,
+                _ => return Err(mismatch(value, "bool")),
+            };
+        } else if type_id == TypeId::of::<f32>() {
+            *data.cast::<f32>() = match value {
+                DynValue::Float(value) => *value as f32,
+                DynValue::Int(value) => *value as f32,
+                DynValue::UInt(value) => *value as f32,
+                _ => return Err(mismatch(value, "f32")),
+            };
+        } else if type_id == TypeId::of::<f64>() {
+            *data.cast::<f64>() = match value {
+                DynValue::Float(value) => *value,
+                DynValue::Int(value) => *value as f64,
+                DynValue::UInt(value) => *value as f64,
+                _ => return Err(mismatch(value, "f64")),
+            };
+        } else if type_id == TypeId::of::<String>() {
+            match value {
+                DynValue::String(value) => (*data.cast::<String>()).clone_from(value),
+                _ => return Err(mismatch(value, "String")),
+            }
+        } else {
+            return Err(Error::TypeError(format!(
+                "Value class {:?} has no dynamic representation!",
+                class
+            )));
+        }
+        Ok(())
+    } else if let Some(object) = class.downcast_ref::<Object>() {
+        let DynValue::Map(entries) = value else {
+            return Err(mismatch(value, &format!("{:?}", object)));
+        };
+        for (name, value) in entries {
+            let pointer = object.attr_at(data, name)?;
+            store(&*pointer.class, pointer.data, value)?;
+        }
+        Ok(())
+    } else if let Some(array) = class.downcast_ref::<Array>() {
+        let DynValue::List(elements) = value else {
+            return Err(mismatch(value, &format!("{:?}", array)));
+        };
+        if elements.len() != array.length {
+            return Err(Error::ValueError(format!(
+                "Cannot store {} elements in {:?}!",
+                elements.len(),
+                array
+            )));
+        }
+        for (i, element) in elements.iter().enumerate() {
+            let pointer = array.item_at(data, i)?;
+            store(&*pointer.class, pointer.data, element)?;
+        }
+        Ok(())
+    } else if let Some(list) = class.downcast_ref::<List>() {
+        let DynValue::List(elements) = value else {
+            return Err(mismatch(value, &format!("{:?}", list)));
+        };
+        while !list.is_empty(data) {
+            list.pop(data)?;
+        }
+        for element in elements {
+            store(&*list.element, list.push(data)?, element)?;
+        }
+        Ok(())
+    } else if let Some(optional) = class.downcast_ref::<Optional>() {
+        match value {
+            DynValue::Null => {
+                optional.clear(data);
+                Ok(())
+            }
+            value => store(&*optional.inner, optional.set(data), value),
+        }
+    } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+        let DynValue::Map(entries) = value else {
+            return Err(mismatch(value, &format!("{:?}", enumeration)));
+        };
+        let [(name, payload)] = entries.as_slice() else {
+            return Err(Error::ValueError(format!(
+                "Enum {:?} must be stored from a map with exactly one variant!",
+                enumeration
+            )));
+        };
+        enumeration.switch(data, name)?;
+        match payload {
+            DynValue::Null => Ok(()),
+            payload => {
+                let pointer = enumeration.attr_at(data, name)?;
+                store(&*pointer.class, pointer.data, payload)
+            }
+        }
+    } else {
+        Err(Error::TypeError(format!(
+            "Class {:?} has no dynamic representation!",
+            class
+        )))
+    }
+}
@@ -5,6 +5,27 @@ pub enum Error {
     AttributeError(String),
     IndexError(String),
     ValueError(String),
+    SyntaxError(String),
+    TruncatedError(String),
+    CorruptError(String),
+    VersionError(String),
+}
+
+impl Error {
+    /// Rewrite the message while keeping the kind of error.
+    pub fn map(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            Error::AccessError(message) => Error::AccessError(f(message)),
+            Error::TypeError(message) => Error::TypeError(f(message)),
+            Error::AttributeError(message) => Error::AttributeError(f(message)),
+            Error::IndexError(message) => Error::IndexError(f(message)),
+            Error::ValueError(message) => Error::ValueError(f(message)),
+            Error::SyntaxError(message) => Error::SyntaxError(f(message)),
+            Error::TruncatedError(message) => Error::TruncatedError(f(message)),
+            Error::CorruptError(message) => Error::CorruptError(f(message)),
+            Error::VersionError(message) => Error::VersionError(f(message)),
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -15,6 +36,10 @@ impl std::fmt::Display for Error {
             Error::AttributeError(message) => write!(f, "AttributeError: {}", message),
             Error::IndexError(message) => write!(f, "IndexError: {}", message),
             Error::ValueError(message) => write!(f, "ValueError: {}", message),
+            Error::SyntaxError(message) => write!(f, "SyntaxError: {}", message),
+            Error::TruncatedError(message) => write!(f, "TruncatedError: {}", message),
+            Error::CorruptError(message) => write!(f, "CorruptError: {}", message),
+            Error::VersionError(message) => write!(f, "VersionError: {}", message),
         }
     }
 }
@@ -0,0 +1,245 @@
+use crate::class::array::Array;
+use crate::class::columnar::{Column, Columnar, Row};
+use crate::class::enumeration::Enum;
+use crate::class::list::List;
+use crate::class::object::Object;
+use crate::class::optional::Optional;
+use crate::class::{Class, Constructed};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter, Result};
+use std::sync::OnceLock;
+
+type Printer = Box<dyn Fn(*const u8, &mut Formatter<'_>) -> Result + Send + Sync>;
+
+/// Printers for value types, looked up by the `TypeId` their class reports.
+pub struct Printers {
+    printers: HashMap<TypeId, Printer>,
+}
+
+impl Printers {
+    pub fn new() -> Self {
+        Printers {
+            printers: HashMap::new(),
+        }
+    }
+
+    /// The printers used when none are specified, covering primitives and `String`.
+    pub fn standard() -> &'static Printers {
+        static PRINTERS: OnceLock<Printers> = OnceLock::new();
+        PRINTERS.get_or_init(Printers::default)
+    }
+
+    /// Print values of type T with its `Debug` implementation.
+    pub fn register<T: Debug + 'static>(&mut self) {
+        self.register_with::<T>(|value, formatter| Debug::fmt(value, formatter));
+    }
+
+    pub fn register_with<T: 'static>(
+        &mut self,
+        printer: impl Fn(&T, &mut Formatter<'_>) -> Result + Send + Sync + 'static,
+    ) {
+        self.printers.insert(
+            TypeId::of::<T>(),
+            // Invariant: only called with data of a class whose value() is T
+            Box::new(move |data, formatter| printer(unsafe { &*data.cast::<T>() }, formatter)),
+        );
+    }
+}
+
+impl Default for Printers {
+    fn default() -> Self {
+        let mut printers = Printers::new();
+        printers.register::<bool>();
+        printers.register::<char>();
+        printers.register::<i8>();
+        printers.register::<i16>();
+        printers.register::<i32>();
+        printers.register::<i64>();
+        printers.register::<i128>();
+        printers.register::<isize>();
+        printers.register::<u8>();
+        printers.register::<u16>();
+        printers.register::<u32>();
+        printers.register::<u64>();
+        printers.register::<u128>();
+        printers.register::<usize>();
+        printers.register::<f32>();
+        printers.register::<f64>();
+        printers.register::<String>();
+        printers
+    }
+}
+
+/// Formats constructed data by walking its class, e.g. `Foo { a: 69, b: -69, c: [1, 2, 3] }`.
+/// Alternate formatting (`{:#?}`) spreads containers over multiple lines.
+#[derive(Clone, Copy)]
+pub struct Pretty<'a> {
+    target: Constructed<'a, *const u8>,
+    printers: &'a Printers,
+    depth: Option<usize>,
+}
+
+impl<'a> Pretty<'a> {
+    /// # Safety
+    ///
+    /// `data` must have been constructed by class and outlive the returned value.
+    pub unsafe fn new(class: &'a dyn Class, data: *const u8) -> Self {
+        Pretty {
+            target: Constructed::new(class, data),
+            printers: Printers::standard(),
+            depth: None,
+        }
+    }
+
+    pub fn printers(self, printers: &'a Printers) -> Self {
+        Pretty { printers, ..self }
+    }
+
+    /// Elide containers nested more than depth levels deep.
+    pub fn depth(self, depth: usize) -> Self {
+        Pretty {
+            depth: Some(depth),
+            ..self
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `data` must be a child of this data, of class.
+    unsafe fn child(&self, class: &'a dyn Class, data: *const u8) -> Self {
+        Pretty {
+            target: Constructed::new(class, data),
+            printers: self.printers,
+            depth: self.depth.map(|depth| depth.saturating_sub(1)),
+        }
+    }
+
+    fn exhausted(&self) -> bool {
+        self.depth == Some(0)
+    }
+}
+
+impl Debug for Pretty<'_> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+        let Constructed { class, data } = self.target;
+        unsafe {
+            if let Some(type_id) = class.value() {
+                match self.printers.printers.get(&type_id) {
+                    Some(printer) => printer(data, formatter),
+                    None => write!(formatter, "<{:?}>", class),
+                }
+            } else if let Some(object) = class.downcast_ref::<Object>() {
+                let mut debug = formatter.debug_struct(&object.name);
+                if self.exhausted() {
+                    return debug.finish_non_exhaustive();
+                }
+                for member in object.members() {
+                    debug.field(
+                        &member.name,
+                        &self.child(&*member.class, data.add(member.offset)),
+                    );
+                }
+                debug.finish()
+            } else if let Some(array) = class.downcast_ref::<Array>() {
+                if self.exhausted() {
+                    return formatter.write_str("[..]");
+                }
+                let stride = array.element.stride();
+                formatter
+                    .debug_list()
+                    .entries(
+                        (0..array.length)
+                            .map(|i| self.child(&*array.element, data.add(stride * i))),
+                    )
+                    .finish()
+            } else if let Some(list) = class.downcast_ref::<List>() {
+                if self.exhausted() {
+                    return formatter.write_str("[..]");
+                }
+                let mut debug = formatter.debug_list();
+                for i in 0..list.len(data) {
+                    let pointer = list.item_at(data.cast_mut(), i).unwrap();
+                    debug.entry(&self.child(&*list.element, pointer.data));
+                }
+                debug.finish()
+            } else if let Some(columnar) = class.downcast_ref::<Columnar>() {
+                if self.exhausted() {
+                    return formatter.write_str("[..]");
+                }
+                formatter
+                    .debug_list()
+                    .entries((0..columnar.length()).map(|index| Item {
+                        pretty: self.child(class, data),
+                        element: columnar.element(),
+                        columns: columnar.columns(),
+                        index,
+                    }))
+                    .finish()
+            } else if let Some(row) = class.downcast_ref::<Row>() {
+                Item {
+                    pretty: *self,
+                    element: row.element(),
+                    columns: row.columns(),
+                    index: row.index,
+                }
+                .fmt(formatter)
+            } else if let Some(optional) = class.downcast_ref::<Optional>() {
+                if optional.is_set(data) {
+                    formatter
+                        .debug_tuple("Some")
+                        .field(&self.child(&*optional.inner, data.add(optional.offset)))
+                        .finish()
+                } else {
+                    formatter.write_str("None")
+                }
+            } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+                let variant = enumeration.variant(data);
+                let name = format!("{}::{}", enumeration.name, variant.name);
+                match &variant.class {
+                    Some(_) if self.exhausted() => write!(formatter, "{}(..)", name),
+                    Some(payload) => formatter
+                        .debug_tuple(&name)
+                        .field(&self.child(&**payload, data.add(enumeration.offset)))
+                        .finish(),
+                    None => formatter.write_str(&name),
+                }
+            } else {
+                write!(formatter, "<{:?}>", class)
+            }
+        }
+    }
+}
+
+/// An item of a columnar container, printed like the object it stores.
+struct Item<'a> {
+    pretty: Pretty<'a>,
+    element: &'a Object,
+    columns: &'a [Column],
+    index: usize,
+}
+
+impl Debug for Item<'_> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+        let mut debug = formatter.debug_struct(&self.element.name);
+        if self.pretty.exhausted() {
+            return debug.finish_non_exhaustive();
+        }
+        for column in self.columns {
+            let element = &*column.class.element;
+            let offset = column.offset + element.stride() * self.index;
+            // Invariant: pretty's data is the whole container, so every column is constructed
+            debug.field(&column.name, &unsafe {
+                self.pretty
+                    .child(element, self.pretty.target.data.add(offset))
+            });
+        }
+        debug.finish()
+    }
+}
+
+impl Display for Pretty<'_> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+        Debug::fmt(self, formatter)
+    }
+}
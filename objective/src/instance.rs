@@ -1,52 +1,440 @@
 pub mod read;
 pub mod write;
 
-use crate::class::Class;
+use crate::allocator::{Allocator, Global};
+use crate::class::array::Array;
+use crate::class::columnar::Row;
+use crate::class::lens::Lens;
+use crate::class::list::List;
+use crate::class::object::Object;
+use crate::class::pointer::Pointer;
+use crate::class::{Class, Operation};
+use crate::error::{self, Error};
+use crate::format::Pretty;
 use crate::instance::read::InstanceReadGuard;
 use crate::instance::write::InstanceWriteGuard;
-use std::alloc::{alloc, dealloc};
-use std::sync::{Arc, PoisonError, RwLock};
+use crate::native::Objective;
+use std::alloc::{handle_alloc_error, Layout};
+use std::any::{type_name, TypeId};
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
+use std::ops::Deref;
+use std::sync::{Arc, PoisonError, RwLock, TryLockError};
 
+/// A constructed object of a class, stored in memory from an [`Allocator`] or a buffer adopted with
+/// [`Instance::in_buffer`]. Dropping an instance always destroys its data, but only frees memory
+/// that came from an allocator.
 pub struct Instance {
     class: Arc<dyn Class>,
     data: RwLock<*mut u8>,
+    storage: Storage,
+}
+
+/// Who frees an instance's data once it has been destroyed.
+#[derive(Clone)]
+pub(crate) enum Storage {
+    /// Returned to the allocator it came from.
+    Allocated(Arc<dyn Allocator>),
+    /// Left to whoever handed the buffer to [`Instance::in_buffer`].
+    Adopted,
+}
+
+impl Storage {
+    /// The allocator for instances derived from one with this storage, e.g. clones.
+    fn allocator(&self) -> Arc<dyn Allocator> {
+        match self {
+            Storage::Allocated(allocator) => allocator.clone(),
+            Storage::Adopted => Global::shared(),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `data` must be the destroyed data of an instance with this storage and layout.
+    pub(crate) unsafe fn release(&self, data: *mut u8, layout: Layout) {
+        if let Storage::Allocated(allocator) = self {
+            deallocate(&**allocator, data, layout);
+        }
+    }
 }
 
 impl Instance {
     pub fn new(class: Arc<dyn Class>) -> Self {
+        Self::new_in(class, Global::shared())
+    }
+
+    /// Construct an instance in memory from allocator, which it is returned to on drop. Aborts
+    /// through `handle_alloc_error` if the allocator has no memory.
+    ///
+    /// The elements of any lists in the instance still come from the global allocator.
+    pub fn new_in(class: Arc<dyn Class>, allocator: Arc<dyn Allocator>) -> Self {
         // Invariant: construct expects to have at least size() data
         // Must be deallocated in drop
         unsafe {
-            let data = alloc(class.layout());
+            let data = allocate(&*allocator, class.layout());
             class.construct(data);
             Self {
                 class,
                 data: RwLock::new(data),
+                storage: Storage::Allocated(allocator),
+            }
+        }
+    }
+
+    /// Construct an instance in a buffer owned by the caller. The instance destroys its data when
+    /// dropped but never frees the buffer, which the caller may reuse or free afterwards.
+    ///
+    /// Only the inline data lives in the buffer: elements of lists come from the global allocator,
+    /// and so do instances derived from this one, such as clones or the result of a
+    /// [`Migration`](crate::migration::Migration), which leaves the buffer to the caller as
+    /// dropping would.
+    ///
+    /// # Panics
+    ///
+    /// If buffer is null or not aligned to the class.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be valid for reads and writes of `class.size()` bytes and must not be accessed
+    /// other than through the instance until it is dropped.
+    pub unsafe fn in_buffer(class: Arc<dyn Class>, buffer: *mut u8) -> Self {
+        assert!(
+            !buffer.is_null() && (buffer as usize).is_multiple_of(class.align()),
+            "Buffer is not aligned to {} for {:?}",
+            class.align(),
+            class
+        );
+        class.construct(buffer);
+        Self {
+            class,
+            data: RwLock::new(buffer),
+            storage: Storage::Adopted,
+        }
+    }
+
+    /// Move a native value into a new instance of its class.
+    pub fn from_native<T: Objective>(value: T) -> Self {
+        let class: Arc<dyn Class> = T::class();
+        let allocator = Global::shared();
+        // Invariant: T::class() has the layout of T, so the value is a constructed instance
+        unsafe {
+            let data = allocate(&*allocator, class.layout());
+            data.cast::<T>().write(value);
+            Self {
+                class,
+                data: RwLock::new(data),
+                storage: Storage::Allocated(allocator),
             }
         }
     }
 
-    pub fn read(&self) -> Result<InstanceReadGuard, PoisonError<InstanceReadGuard>> {
+    pub fn read(&self) -> Result<InstanceReadGuard<'_>, PoisonError<InstanceReadGuard<'_>>> {
         InstanceReadGuard::acquire(self)
     }
 
-    pub fn write(&self) -> Result<InstanceWriteGuard, PoisonError<InstanceWriteGuard>> {
+    pub fn write(&self) -> Result<InstanceWriteGuard<'_>, PoisonError<InstanceWriteGuard<'_>>> {
         InstanceWriteGuard::acquire(self)
     }
+
+    /// Deep copy the instance if its class supports cloning. The copy comes from the same
+    /// allocator, or the global one if this instance is in an adopted buffer.
+    pub fn try_clone(&self) -> error::Result<Instance> {
+        self.require(Operation::Clone)?;
+        let source = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        let layout = self.class.layout();
+        let allocator = self.allocator();
+        // Invariant: source was constructed by class, data is deallocated if cloning fails
+        unsafe {
+            let data = allocate(&*allocator, layout);
+            if let Err(error) = self.class.clone_data(*source, data) {
+                deallocate(&*allocator, data, layout);
+                return Err(error);
+            }
+            Ok(Instance {
+                class: self.class.clone(),
+                data: RwLock::new(data),
+                storage: Storage::Allocated(allocator),
+            })
+        }
+    }
+
+    /// Compare the contents of two instances if their class supports equality. Instances of
+    /// different classes are never equal.
+    pub fn try_eq(&self, other: &Instance) -> error::Result<bool> {
+        self.require(Operation::Eq)?;
+        if self.class.id() != other.class.id() {
+            return Ok(false);
+        }
+        let left = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        // Locking the same instance twice could deadlock with a waiting writer
+        if std::ptr::eq(self, other) {
+            return unsafe { self.class.eq_data(*left, *left) };
+        }
+        let right = other.data.read().unwrap_or_else(PoisonError::into_inner);
+        unsafe { self.class.eq_data(*left, *right) }
+    }
+
+    pub fn try_hash<H: Hasher>(&self, state: &mut H) -> error::Result<()> {
+        self.require(Operation::Hash)?;
+        let data = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        unsafe { self.class.hash_data(*data, state) }
+    }
+
+    pub(crate) fn allocator(&self) -> Arc<dyn Allocator> {
+        self.storage.allocator()
+    }
+
+    /// Give up ownership of the data without destroying it. The caller must destroy it through
+    /// the returned class and then release it through the returned storage.
+    pub(crate) fn into_raw(self) -> (Arc<dyn Class>, *mut u8, Storage) {
+        let mut instance = std::mem::ManuallyDrop::new(self);
+        let data = *match instance.data.get_mut() {
+            Ok(data) => data,
+            Err(error) => error.into_inner(),
+        };
+        // Invariant: instance is never dropped, so its fields are moved out exactly once
+        unsafe {
+            (
+                std::ptr::read(&instance.class),
+                data,
+                std::ptr::read(&instance.storage),
+            )
+        }
+    }
+
+    fn require(&self, operation: Operation) -> error::Result<()> {
+        if self.class.supports(operation) {
+            Ok(())
+        } else {
+            Err(Error::TypeError(format!(
+                "Class {:?} does not support {}!",
+                self.class, operation
+            )))
+        }
+    }
+}
+
+/// An instance whose class supports cloning, checked once when it is wrapped so that it can
+/// implement [`Clone`].
+#[derive(Debug)]
+pub struct Cloneable(Instance);
+
+impl Cloneable {
+    pub fn new(instance: Instance) -> error::Result<Self> {
+        instance.require(Operation::Clone)?;
+        Ok(Cloneable(instance))
+    }
+
+    pub fn into_inner(self) -> Instance {
+        self.0
+    }
+}
+
+impl Deref for Cloneable {
+    type Target = Instance;
+
+    fn deref(&self) -> &Instance {
+        &self.0
+    }
+}
+
+/// Panics only if the class fails to clone data after reporting that it supports cloning.
+impl Clone for Cloneable {
+    fn clone(&self) -> Self {
+        Cloneable(
+            self.0
+                .try_clone()
+                .unwrap_or_else(|error| panic!("{}", error)),
+        )
+    }
+}
+
+/// An instance whose class supports equality and hashing, checked once when it is wrapped so that
+/// it can implement [`Eq`] and [`Hash`]. Writing to it changes its hash, so it must not be written
+/// to while it is used as a key.
+#[derive(Debug)]
+pub struct Comparable(Instance);
+
+impl Comparable {
+    pub fn new(instance: Instance) -> error::Result<Self> {
+        instance.require(Operation::Eq)?;
+        instance.require(Operation::Hash)?;
+        Ok(Comparable(instance))
+    }
+
+    pub fn into_inner(self) -> Instance {
+        self.0
+    }
+}
+
+impl Deref for Comparable {
+    type Target = Instance;
+
+    fn deref(&self) -> &Instance {
+        &self.0
+    }
+}
+
+/// Panics only if the class fails to compare data after reporting that it supports equality.
+impl PartialEq for Comparable {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .try_eq(&other.0)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+impl Eq for Comparable {}
+
+/// Panics only if the class fails to hash data after reporting that it supports hashing.
+impl Hash for Comparable {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0
+            .try_hash(state)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+}
+
+impl Instance {
+    // Formatting must not block, e.g. when the instance is printed while this thread writes to it
+    fn format(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        let data = match self.data.try_read() {
+            Ok(data) => data,
+            Err(TryLockError::Poisoned(error)) => error.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                return write!(formatter, "<locked {:?}>", self.class);
+            }
+        };
+        // Invariant: data was constructed in new and is read-locked while formatting
+        Debug::fmt(&unsafe { Pretty::new(&*self.class, *data) }, formatter)
+    }
+}
+
+impl Debug for Instance {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        self.format(formatter)
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        self.format(formatter)
+    }
 }
 
 impl Drop for Instance {
     fn drop(&mut self) {
-        // Invariant: is not null, has layout of self.class.layout()
+        // Invariant: is not null, has layout of self.class.layout(), was constructed in new
+        // A poisoned lock still owns constructed data, so members are destroyed either way
+        let data = *match self.data.get_mut() {
+            Ok(data) => data,
+            Err(error) => error.into_inner(),
+        };
         unsafe {
-            // TODO: not sure what to do here
-            dealloc(
-                *match self.data.write() {
-                    Ok(data) => data,
-                    Err(error) => error.into_inner()
-                },
-                self.class.layout()
-            );
+            self.class.destroy(data);
+            self.storage.release(data, self.class.layout());
         }
     }
 }
+
+/// Check that class stores values of type U, so that its data may be cast to U.
+pub(crate) fn check_value<U: 'static>(class: &dyn Class) -> error::Result<()> {
+    match class.value() {
+        Some(type_id) if type_id == TypeId::of::<U>() => Ok(()),
+        Some(_) => Err(Error::ValueError(format!(
+            "Cannot cast underlying type {} to {:?}!",
+            type_name::<U>(),
+            class,
+        ))),
+        None => Err(Error::TypeError(format!(
+            "Cannot cast untyped class {:?}!",
+            class
+        ))),
+    }
+}
+
+/// Check that class stores values of type U packed without padding, so that consecutive elements
+/// of class may be viewed as a slice of U.
+pub(crate) fn check_slice<U: 'static>(class: &dyn Class) -> error::Result<()> {
+    check_value::<U>(class)?;
+    // Classes only promise that their data is a valid U, so they may pad it to a larger stride
+    if class.stride() != size_of::<U>() {
+        return Err(Error::TypeError(format!(
+            "Elements of class {:?} are padded and cannot be viewed as a slice!",
+            class
+        )));
+    }
+    Ok(())
+}
+
+/// The name and lens of each member of an object or of a columnar row, in declaration order.
+pub(crate) fn members(
+    class: &dyn Class,
+) -> error::Result<Box<dyn Iterator<Item = (&str, Lens)> + '_>> {
+    if let Some(object) = class.downcast_ref::<Object>() {
+        Ok(Box::new(object.members().iter().map(|member| {
+            let lens = Lens {
+                class: member.class.clone(),
+                offset: member.offset,
+            };
+            (member.name.as_str(), lens)
+        })))
+    } else if let Some(row) = class.downcast_ref::<Row>() {
+        Ok(Box::new(
+            row.columns()
+                .iter()
+                .map(|column| (column.name.as_str(), row.lens(column))),
+        ))
+    } else {
+        Err(Error::TypeError(format!(
+            "Class {:?} is not an object!",
+            class
+        )))
+    }
+}
+
+/// A pointer to each element of an array or list, in order.
+///
+/// # Safety
+///
+/// `data` must have been constructed by class, and a list must not change length while the
+/// pointers are in use.
+pub(crate) unsafe fn elements(
+    class: &dyn Class,
+    data: *mut u8,
+) -> error::Result<impl Iterator<Item = Pointer> + '_> {
+    let (element, start, length) = if let Some(array) = class.downcast_ref::<Array>() {
+        (&array.element, data, array.length)
+    } else if let Some(list) = class.downcast_ref::<List>() {
+        (&list.element, list.elements(data), list.len(data))
+    } else {
+        return Err(Error::TypeError(format!(
+            "Class {:?} does not have a length!",
+            class
+        )));
+    };
+    let stride = element.stride();
+    Ok((0..length).map(move |index| Pointer {
+        class: element.clone(),
+        data: start.add(stride * index),
+    }))
+}
+
+// Allocators are not asked for zero-sized layouts, so those get a dangling aligned pointer
+unsafe fn allocate(allocator: &dyn Allocator, layout: Layout) -> *mut u8 {
+    if layout.size() == 0 {
+        return layout.align() as *mut u8;
+    }
+    let data = allocator.allocate(layout);
+    if data.is_null() {
+        handle_alloc_error(layout);
+    }
+    data
+}
+
+unsafe fn deallocate(allocator: &dyn Allocator, data: *mut u8, layout: Layout) {
+    if layout.size() != 0 {
+        allocator.deallocate(data, layout);
+    }
+}
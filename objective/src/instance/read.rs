@@ -1,10 +1,22 @@
-use crate::accessor::{Accessor, Cast, IntoAccessor};
-use crate::class::lens::Lens;
+use crate::accessor::{Cast, IntoAccessor};
+use crate::class::array::Array;
+use crate::class::columnar::Columnar;
+use crate::class::enumeration::Enum;
+use crate::class::list::List;
+use crate::class::optional::Optional;
+use crate::class::pointer::Pointer;
 use crate::class::view::View;
-use crate::class::Class;
+use crate::class::{Class, Unique};
+use crate::dynamic::{self, DynValue};
 use crate::error::{Error, Result};
-use crate::instance::Instance;
-use std::any::{type_name, TypeId};
+use crate::format::Pretty;
+use crate::instance::{self, Instance};
+use crate::native::Objective;
+use crate::numeric::{self, Numeric};
+use crate::path::Path;
+#[cfg(feature = "serde")]
+use crate::serde::Serializable;
+use std::any::type_name;
 use std::borrow::Borrow;
 use std::sync::{Arc, PoisonError, RwLockReadGuard};
 
@@ -27,21 +39,41 @@ impl<'g> InstanceReadGuard<'g> {
         }
     }
 
-    unsafe fn cast<U: 'static>(&self, class: &dyn Class, offset: usize) -> Result<&'g U> {
-        if let Some(type_id) = class.value() {
-            if type_id == TypeId::of::<U>() {
-                Ok(&*self.data.add(offset).cast::<U>())
-            } else {
-                Err(Error::ValueError(format!(
-                    "Cannot cast underlying type {} to {:?}!",
-                    type_name::<U>(),
-                    class,
-                )))
-            }
+    pub fn class(&self) -> &Arc<dyn Class> {
+        &self.class
+    }
+
+    pub(crate) fn data(&self) -> *mut u8 {
+        *self.data
+    }
+
+    unsafe fn cast<U: 'static>(&self, class: &dyn Class, data: *mut u8) -> Result<&'g U> {
+        instance::check_value::<U>(class)?;
+        Ok(&*data.cast::<U>())
+    }
+
+    /// # Safety
+    ///
+    /// `data` must hold length consecutive elements constructed by class.
+    unsafe fn cast_slice<U: 'static>(
+        &self,
+        class: &dyn Class,
+        data: *mut u8,
+        length: usize,
+    ) -> Result<&'g [U]> {
+        instance::check_slice::<U>(class)?;
+        Ok(std::slice::from_raw_parts(data.cast::<U>(), length))
+    }
+
+    /// View the whole instance as the native type its class was derived from.
+    pub fn reinterpret<T: Objective>(&self) -> Result<&T> {
+        if self.class.id() == T::class().id() {
+            Ok(unsafe { &*self.data.cast::<T>() })
         } else {
             Err(Error::TypeError(format!(
-                "Cannot cast untyped class {:?}!",
-                class
+                "Instance of type {:?} cannot be reinterpreted as {}!",
+                self.class,
+                type_name::<T>()
             )))
         }
     }
@@ -57,11 +89,26 @@ impl<'g> InstanceReadGuard<'g> {
     pub fn through(&self, lens: &View) -> Result<ReadReference<'_>> {
         ReadReference::apply(lens, self)
     }
+
+    /// Format the whole instance through its class.
+    pub fn pretty(&self) -> Pretty<'_> {
+        // Invariant: the data stays constructed for as long as the guard is held
+        unsafe { Pretty::new(&*self.class, *self.data) }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn serializable(&self) -> Serializable<'_> {
+        unsafe { Serializable::new(&*self.class, *self.data) }
+    }
+
+    pub fn path(&self, path: &Path) -> Result<ReadReference<'_>> {
+        path.apply(ReadReference::of(self))
+    }
 }
 
 unsafe impl<'g> Cast for InstanceReadGuard<'g> {
     fn cast<U: 'static>(&self) -> Result<&U> {
-        unsafe { self.cast(self.class.borrow(), 0) }
+        unsafe { self.cast(self.class.borrow(), *self.data) }
     }
 }
 
@@ -69,24 +116,24 @@ unsafe impl<'g> Cast for InstanceReadGuard<'g> {
 pub struct ReadReference<'g> {
     instance: &'g InstanceReadGuard<'g>,
     class: Arc<dyn Class>,
-    offset: usize,
+    data: *mut u8,
 }
 
 impl<'g> ReadReference<'g> {
     pub fn of(instance: &'g InstanceReadGuard<'g>) -> Self {
         ReadReference {
             class: instance.class.clone(),
+            data: *instance.data,
             instance,
-            offset: 0,
         }
     }
 
     pub fn apply(lens: &View, instance: &'g InstanceReadGuard<'g>) -> Result<Self> {
-        if lens.origin.id() == instance.class.id() {
+        if instance.class.is_compatible_with(&*lens.origin) {
             Ok(ReadReference {
                 instance,
                 class: lens.class.clone(),
-                offset: lens.offset,
+                data: unsafe { instance.data.add(lens.offset) },
             })
         } else {
             Err(Error::TypeError(format!(
@@ -96,28 +143,160 @@ impl<'g> ReadReference<'g> {
         }
     }
 
-    unsafe fn access(self, lens: Lens) -> Self {
+    pub fn class(&self) -> &Arc<dyn Class> {
+        &self.class
+    }
+
+    /// Reference the same data as one of the bases of this reference's class, or as any class it is
+    /// structurally compatible with.
+    pub fn upcast(&self, base: &Arc<dyn Class>) -> Result<ReadReference<'g>> {
+        if self.class.is_compatible_with(&**base) {
+            Ok(self.clone().access(Pointer {
+                class: base.clone(),
+                data: self.data,
+            }))
+        } else {
+            Err(Error::TypeError(format!(
+                "Class {:?} is not compatible with {:?}!",
+                self.class, base
+            )))
+        }
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        if let Some(array) = self.class.downcast_ref::<Array>() {
+            Ok(array.length)
+        } else if let Some(list) = self.class.downcast_ref::<List>() {
+            Ok(unsafe { list.len(self.data) })
+        } else {
+            Err(Error::TypeError(format!(
+                "Class {:?} does not have a length!",
+                self.class
+            )))
+        }
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        self.len().map(|length| length == 0)
+    }
+
+    /// References to each element of an array or list, in order.
+    pub fn items(&self) -> Result<impl Iterator<Item = ReadReference<'g>> + '_> {
+        // Invariant: the instance is read-locked, so a list cannot change length
+        let elements = unsafe { instance::elements(&*self.class, self.data)? };
+        Ok(elements.map(|pointer| self.clone().access(pointer)))
+    }
+
+    /// The name of and a reference to each member of an object or columnar row, in declaration
+    /// order.
+    pub fn members(&self) -> Result<impl Iterator<Item = (&str, ReadReference<'g>)> + '_> {
+        Ok(instance::members(&*self.class)?.map(move |(name, lens)| {
+            let pointer = Pointer {
+                class: lens.class,
+                // Invariant: members lie within the object's data
+                data: unsafe { self.data.add(lens.offset) },
+            };
+            (name, self.clone().access(pointer))
+        }))
+    }
+
+    pub fn variant(&self) -> Result<&str> {
+        match self.class.downcast_ref::<Enum>() {
+            Some(enumeration) => Ok(unsafe { &enumeration.variant(self.data).name }),
+            None => Err(Error::TypeError(format!(
+                "Class {:?} is not an enum!",
+                self.class
+            ))),
+        }
+    }
+
+    fn optional(&self) -> Result<&Optional> {
+        self.class
+            .downcast_ref::<Optional>()
+            .ok_or_else(|| Error::TypeError(format!("Class {:?} is not optional!", self.class)))
+    }
+
+    pub fn is_set(&self) -> Result<bool> {
+        Ok(unsafe { self.optional()?.is_set(self.data) })
+    }
+
+    pub fn get(&self) -> Result<ReadReference<'g>> {
+        let pointer = unsafe { self.optional()?.get(self.data)? };
+        Ok(self.clone().access(pointer))
+    }
+
+    /// View an array of values as a slice of their type.
+    pub fn as_slice<T: 'static>(&self) -> Result<&[T]> {
+        let array = self
+            .class
+            .downcast_ref::<Array>()
+            .ok_or_else(|| Error::TypeError(format!("Class {:?} is not an array!", self.class)))?;
+        // Invariant: an array's elements are constructed back to back from its start
+        unsafe {
+            self.instance
+                .cast_slice(&*array.element, self.data, array.length)
+        }
+    }
+
+    /// View one column of a columnar container as a slice of its value type.
+    pub fn column<T: 'static>(&self, name: &str) -> Result<&[T]> {
+        let columnar = self
+            .class
+            .downcast_ref::<Columnar>()
+            .ok_or_else(|| Error::TypeError(format!("Class {:?} is not columnar!", self.class)))?;
+        let column = columnar.column(name).ok_or_else(|| {
+            Error::AttributeError(format!("Columnar {:?} has no column {}", columnar, name))
+        })?;
+        // Invariant: the column is an array of constructed elements at its offset
+        unsafe {
+            self.instance.cast_slice(
+                &*column.class.element,
+                self.data.add(column.offset),
+                column.class.length,
+            )
+        }
+    }
+
+    pub fn get_dynamic(&self) -> Result<DynValue> {
+        unsafe { dynamic::load(&*self.class, self.data) }
+    }
+
+    pub fn pretty(&self) -> Pretty<'_> {
+        unsafe { Pretty::new(&*self.class, self.data) }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn serializable(&self) -> Serializable<'_> {
+        unsafe { Serializable::new(&*self.class, self.data) }
+    }
+
+    /// Read a numeric value, widening or checked narrowing it to U.
+    pub fn read_as<U: Numeric>(&self) -> Result<U> {
+        numeric::convert(unsafe { numeric::load(&*self.class, self.data)? })
+    }
+
+    fn access(self, pointer: Pointer) -> Self {
         ReadReference {
-            instance: &self.instance,
-            class: lens.class,
-            offset: self.offset + lens.offset,
+            instance: self.instance,
+            class: pointer.class,
+            data: pointer.data,
         }
     }
 }
 
 unsafe impl<'g> Cast for ReadReference<'g> {
     fn cast<U: 'static>(&self) -> Result<&U> {
-        unsafe { self.instance.cast(self.class.borrow(), self.offset) }
+        unsafe { self.instance.cast(self.class.borrow(), self.data) }
     }
 }
 
 unsafe impl<'g> IntoAccessor<ReadReference<'g>> for ReadReference<'g> {
     fn attr(self, name: &str) -> Result<Self> {
-        Accessor::<Lens>::attr(&*self.class, name).map(|lens| unsafe { self.access(lens) })
+        unsafe { self.class.attr_at(self.data, name) }.map(|pointer| self.access(pointer))
     }
 
     fn item(self, index: usize) -> Result<ReadReference<'g>> {
-        Accessor::<Lens>::item(&*self.class, index).map(|lens| unsafe { self.access(lens) })
+        unsafe { self.class.item_at(self.data, index) }.map(|pointer| self.access(pointer))
     }
 }
 
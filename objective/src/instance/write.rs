@@ -1,11 +1,22 @@
-use crate::accessor::{Accessor, IntoAccessor, MutableCast};
-use crate::class::lens::Lens;
+use crate::accessor::{IntoAccessor, MutableCast};
+use crate::class::array::Array;
+use crate::class::columnar::Columnar;
+use crate::class::enumeration::Enum;
+use crate::class::list::List;
+use crate::class::optional::Optional;
+use crate::class::pointer::Pointer;
 use crate::class::view::View;
-use crate::class::Class;
+use crate::class::{Class, Unique};
+use crate::dynamic::{self, DynValue};
 use crate::error::{Error, Result};
-use crate::instance::Instance;
-use std::any::{type_name, TypeId};
+use crate::format::Pretty;
+use crate::instance::{self, Instance};
+use crate::native::Objective;
+use crate::numeric::{self, Numeric};
+use crate::path::Path;
+use std::any::type_name;
 use std::borrow::Borrow;
+use std::marker::PhantomData;
 use std::sync::{Arc, PoisonError, RwLockWriteGuard};
 
 pub struct InstanceWriteGuard<'g> {
@@ -27,66 +38,103 @@ impl<'g> InstanceWriteGuard<'g> {
         }
     }
 
-    unsafe fn cast<U: 'static>(&self, class: &dyn Class, offset: usize) -> Result<&'g mut U> {
-        if let Some(type_id) = class.value() {
-            if type_id == TypeId::of::<U>() {
-                Ok(&mut *self.data.add(offset).cast::<U>())
-            } else {
-                Err(Error::ValueError(format!(
-                    "Cannot cast underlying type {} to {:?}!",
-                    type_name::<U>(),
-                    class,
-                )))
-            }
+    pub fn class(&self) -> &Arc<dyn Class> {
+        &self.class
+    }
+
+    pub(crate) fn data(&self) -> *mut u8 {
+        *self.data
+    }
+
+    /// View the whole instance as the native type its class was derived from.
+    pub fn reinterpret<T: Objective>(&mut self) -> Result<&mut T> {
+        if self.class.id() == T::class().id() {
+            Ok(unsafe { &mut *self.data.cast::<T>() })
         } else {
             Err(Error::TypeError(format!(
-                "Cannot cast untyped class {:?}!",
-                class
+                "Instance of type {:?} cannot be reinterpreted as {}!",
+                self.class,
+                type_name::<T>()
             )))
         }
     }
 
-    pub fn attr(&self, name: &str) -> Result<WriteReference<'_>> {
+    pub fn attr(&mut self, name: &str) -> Result<WriteReference<'_>> {
         WriteReference::of(self).attr(name)
     }
 
-    pub fn item(&self, index: usize) -> Result<WriteReference<'_>> {
+    pub fn item(&mut self, index: usize) -> Result<WriteReference<'_>> {
         WriteReference::of(self).item(index)
     }
 
-    pub fn through(&self, lens: &View) -> Result<WriteReference<'_>> {
+    pub fn through(&mut self, lens: &View) -> Result<WriteReference<'_>> {
         WriteReference::apply(lens, self)
     }
+
+    /// Format the whole instance through its class.
+    pub fn pretty(&self) -> Pretty<'_> {
+        // Invariant: the data stays constructed for as long as the guard is held
+        unsafe { Pretty::new(&*self.class, *self.data) }
+    }
+
+    pub fn path(&mut self, path: &Path) -> Result<WriteReference<'_>> {
+        path.apply(WriteReference::of(self))
+    }
 }
 
 unsafe impl<'g> MutableCast for InstanceWriteGuard<'g> {
-    fn cast<U: 'static>(&self) -> Result<&mut U> {
-        unsafe { self.cast(self.class.borrow(), 0) }
+    fn cast<U: 'static>(&mut self) -> Result<&mut U> {
+        unsafe { cast(self.class.borrow(), *self.data) }
     }
 }
 
-#[derive(Clone)]
-pub struct WriteReference<'g> {
-    instance: &'g InstanceWriteGuard<'g>,
+/// # Safety
+///
+/// `data` must have been constructed by class and be borrowed exclusively for 'a.
+unsafe fn cast<'a, U: 'static>(class: &dyn Class, data: *mut u8) -> Result<&'a mut U> {
+    instance::check_value::<U>(class)?;
+    Ok(&mut *data.cast::<U>())
+}
+
+/// # Safety
+///
+/// `data` must hold length consecutive elements constructed by class, borrowed exclusively for 'a.
+unsafe fn cast_slice<'a, U: 'static>(
+    class: &dyn Class,
+    data: *mut u8,
+    length: usize,
+) -> Result<&'a mut [U]> {
+    instance::check_slice::<U>(class)?;
+    Ok(std::slice::from_raw_parts_mut(data.cast::<U>(), length))
+}
+
+/// Exclusive access to part of a write-locked instance, borrowed from its guard like a `&mut`.
+///
+/// Only one reference to any data is usable at a time: navigating moves a reference into the one
+/// it leads to, [`reborrow`](Self::reborrow) lends it out instead, and anything that could free or
+/// move data, such as popping from a list, borrows the reference mutably for as long as its result
+/// lives.
+pub struct WriteReference<'a> {
     class: Arc<dyn Class>,
-    offset: usize,
+    data: *mut u8,
+    borrow: PhantomData<&'a mut u8>,
 }
 
-impl<'g> WriteReference<'g> {
-    pub fn of(instance: &'g InstanceWriteGuard<'g>) -> Self {
+impl<'a> WriteReference<'a> {
+    pub fn of(instance: &'a mut InstanceWriteGuard<'_>) -> Self {
         WriteReference {
             class: instance.class.clone(),
-            instance,
-            offset: 0,
+            data: *instance.data,
+            borrow: PhantomData,
         }
     }
 
-    pub fn apply(lens: &View, instance: &'g InstanceWriteGuard<'g>) -> Result<Self> {
-        if lens.origin.id() == instance.class.id() {
+    pub fn apply(lens: &View, instance: &'a mut InstanceWriteGuard<'_>) -> Result<Self> {
+        if instance.class.is_compatible_with(&*lens.origin) {
             Ok(WriteReference {
-                instance,
                 class: lens.class.clone(),
-                offset: lens.offset,
+                data: unsafe { instance.data.add(lens.offset) },
+                borrow: PhantomData,
             })
         } else {
             Err(Error::TypeError(format!(
@@ -96,37 +144,257 @@ impl<'g> WriteReference<'g> {
         }
     }
 
-    unsafe fn access(self, lens: Lens) -> Self {
+    /// Lend out this reference for a shorter time, leaving it usable again afterwards.
+    pub fn reborrow(&mut self) -> WriteReference<'_> {
         WriteReference {
-            instance: &self.instance,
-            class: lens.class,
-            offset: self.offset + lens.offset,
+            class: self.class.clone(),
+            data: self.data,
+            borrow: PhantomData,
+        }
+    }
+
+    /// Reference the same data as one of the bases of this reference's class, or as any class it is
+    /// structurally compatible with.
+    pub fn upcast(self, base: &Arc<dyn Class>) -> Result<WriteReference<'a>> {
+        if self.class.is_compatible_with(&**base) {
+            let data = self.data;
+            Ok(self.access(Pointer {
+                class: base.clone(),
+                data,
+            }))
+        } else {
+            Err(Error::TypeError(format!(
+                "Class {:?} is not compatible with {:?}!",
+                self.class, base
+            )))
+        }
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        if let Some(array) = self.class.downcast_ref::<Array>() {
+            Ok(array.length)
+        } else if let Some(list) = self.class.downcast_ref::<List>() {
+            Ok(unsafe { list.len(self.data) })
+        } else {
+            Err(Error::TypeError(format!(
+                "Class {:?} does not have a length!",
+                self.class
+            )))
         }
     }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        self.len().map(|length| length == 0)
+    }
+
+    /// References to each element of an array or list, in order, which borrow this reference until
+    /// they are all dropped.
+    pub fn items(&mut self) -> Result<impl Iterator<Item = WriteReference<'_>> + '_> {
+        // Invariant: elements are disjoint and the list cannot change length while borrowed
+        let elements = unsafe { instance::elements(&*self.class, self.data)? };
+        Ok(elements.map(|pointer| unsafe { lend(pointer) }))
+    }
+
+    /// The name of and a reference to each member of an object or columnar row, in declaration
+    /// order, which borrow this reference until they are all dropped.
+    pub fn members(&mut self) -> Result<impl Iterator<Item = (&str, WriteReference<'_>)> + '_> {
+        let data = self.data;
+        Ok(instance::members(&*self.class)?.map(move |(name, lens)| {
+            let pointer = Pointer {
+                class: lens.class,
+                // Invariant: members lie within the object's data and do not overlap
+                data: unsafe { data.add(lens.offset) },
+            };
+            (name, unsafe { lend(pointer) })
+        }))
+    }
+
+    fn list(&self) -> Result<&List> {
+        self.class
+            .downcast_ref::<List>()
+            .ok_or_else(|| Error::TypeError(format!("Class {:?} is not a list!", self.class)))
+    }
+
+    /// Append a default element, returning a reference to it that must be dropped before the list
+    /// changes again.
+    pub fn push(&mut self) -> Result<WriteReference<'_>> {
+        let list = self.list()?;
+        let data = unsafe { list.push(self.data)? };
+        Ok(unsafe {
+            lend(Pointer {
+                class: list.element.clone(),
+                data,
+            })
+        })
+    }
+
+    pub fn pop(&mut self) -> Result<()> {
+        unsafe { self.list()?.pop(self.data) }
+    }
+
+    pub fn insert(&mut self, index: usize) -> Result<WriteReference<'_>> {
+        let list = self.list()?;
+        let data = unsafe { list.insert(self.data, index)? };
+        Ok(unsafe {
+            lend(Pointer {
+                class: list.element.clone(),
+                data,
+            })
+        })
+    }
+
+    pub fn remove(&mut self, index: usize) -> Result<()> {
+        unsafe { self.list()?.remove(self.data, index) }
+    }
+
+    pub fn variant(&self) -> Result<&str> {
+        match self.class.downcast_ref::<Enum>() {
+            Some(enumeration) => Ok(unsafe { &enumeration.variant(self.data).name }),
+            None => Err(Error::TypeError(format!(
+                "Class {:?} is not an enum!",
+                self.class
+            ))),
+        }
+    }
+
+    /// Destroy the active payload and construct the payload of variant in its place.
+    pub fn switch(&mut self, variant: &str) -> Result<()> {
+        match self.class.downcast_ref::<Enum>() {
+            Some(enumeration) => unsafe { enumeration.switch(self.data, variant) },
+            None => Err(Error::TypeError(format!(
+                "Class {:?} is not an enum!",
+                self.class
+            ))),
+        }
+    }
+
+    fn optional(&self) -> Result<&Optional> {
+        self.class
+            .downcast_ref::<Optional>()
+            .ok_or_else(|| Error::TypeError(format!("Class {:?} is not optional!", self.class)))
+    }
+
+    pub fn is_set(&self) -> Result<bool> {
+        Ok(unsafe { self.optional()?.is_set(self.data) })
+    }
+
+    pub fn get(&mut self) -> Result<WriteReference<'_>> {
+        let pointer = unsafe { self.optional()?.get(self.data)? };
+        Ok(unsafe { lend(pointer) })
+    }
+
+    /// Construct the inner value unless it is already set, returning a reference to it that must
+    /// be dropped before the optional is cleared.
+    pub fn set(&mut self) -> Result<WriteReference<'_>> {
+        let optional = self.optional()?;
+        let data = unsafe { optional.set(self.data) };
+        Ok(unsafe {
+            lend(Pointer {
+                class: optional.inner.clone(),
+                data,
+            })
+        })
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        unsafe { self.optional()?.clear(self.data) };
+        Ok(())
+    }
+
+    /// View an array of values as a slice of their type.
+    pub fn as_mut_slice<T: 'static>(&mut self) -> Result<&mut [T]> {
+        let array = self
+            .class
+            .downcast_ref::<Array>()
+            .ok_or_else(|| Error::TypeError(format!("Class {:?} is not an array!", self.class)))?;
+        // Invariant: an array's elements are constructed back to back from its start
+        unsafe { cast_slice(&*array.element, self.data, array.length) }
+    }
+
+    /// View one column of a columnar container as a slice of its value type.
+    pub fn column_mut<T: 'static>(&mut self, name: &str) -> Result<&mut [T]> {
+        let columnar = self
+            .class
+            .downcast_ref::<Columnar>()
+            .ok_or_else(|| Error::TypeError(format!("Class {:?} is not columnar!", self.class)))?;
+        let column = columnar.column(name).ok_or_else(|| {
+            Error::AttributeError(format!("Columnar {:?} has no column {}", columnar, name))
+        })?;
+        // Invariant: the column is an array of constructed elements at its offset
+        unsafe {
+            cast_slice(
+                &*column.class.element,
+                self.data.add(column.offset),
+                column.class.length,
+            )
+        }
+    }
+
+    pub fn get_dynamic(&self) -> Result<DynValue> {
+        unsafe { dynamic::load(&*self.class, self.data) }
+    }
+
+    pub fn set_dynamic(&mut self, value: &DynValue) -> Result<()> {
+        unsafe { dynamic::store(&*self.class, self.data, value) }
+    }
+
+    pub fn pretty(&self) -> Pretty<'_> {
+        unsafe { Pretty::new(&*self.class, self.data) }
+    }
+
+    /// Read a numeric value, widening or checked narrowing it to U.
+    pub fn read_as<U: Numeric>(&self) -> Result<U> {
+        numeric::convert(unsafe { numeric::load(&*self.class, self.data)? })
+    }
+
+    /// Write a numeric value, widening or checked narrowing it to the type of this reference.
+    pub fn write_from<U: Numeric>(&mut self, value: U) -> Result<()> {
+        unsafe { numeric::store(&*self.class, self.data, value.to_number()) }
+    }
+
+    // Pointers from this reference's class lie within the data it borrows
+    fn access(self, pointer: Pointer) -> Self {
+        unsafe { lend(pointer) }
+    }
+}
+
+/// Reference data the caller borrows exclusively, e.g. a migration filling in a new instance under
+/// its own lock.
+///
+/// # Safety
+///
+/// The pointer's data must have been constructed by its class and not be accessed other than
+/// through the reference for 'a.
+pub(crate) unsafe fn lend<'a>(pointer: Pointer) -> WriteReference<'a> {
+    WriteReference {
+        class: pointer.class,
+        data: pointer.data,
+        borrow: PhantomData,
+    }
 }
 
-unsafe impl<'g> MutableCast for WriteReference<'g> {
-    fn cast<U: 'static>(&self) -> Result<&mut U> {
-        unsafe { self.instance.cast(self.class.borrow(), self.offset) }
+unsafe impl<'a> MutableCast for WriteReference<'a> {
+    fn cast<U: 'static>(&mut self) -> Result<&mut U> {
+        unsafe { cast(self.class.borrow(), self.data) }
     }
 }
 
-unsafe impl<'g> IntoAccessor<WriteReference<'g>> for WriteReference<'g> {
-    fn attr(self, name: &str) -> Result<WriteReference<'g>> {
-        Accessor::<Lens>::attr(&*self.class, name).map(|lens| unsafe { self.access(lens) })
+unsafe impl<'a> IntoAccessor<WriteReference<'a>> for WriteReference<'a> {
+    fn attr(self, name: &str) -> Result<WriteReference<'a>> {
+        unsafe { self.class.attr_at(self.data, name) }.map(|pointer| self.access(pointer))
     }
 
-    fn item(self, index: usize) -> Result<WriteReference<'g>> {
-        Accessor::<Lens>::item(&*self.class, index).map(|lens| unsafe { self.access(lens) })
+    fn item(self, index: usize) -> Result<WriteReference<'a>> {
+        unsafe { self.class.item_at(self.data, index) }.map(|pointer| self.access(pointer))
     }
 }
 
-unsafe impl<'g> IntoAccessor<WriteReference<'g>> for Result<WriteReference<'g>> {
-    fn attr(self, name: &str) -> Result<WriteReference<'g>> {
+unsafe impl<'a> IntoAccessor<WriteReference<'a>> for Result<WriteReference<'a>> {
+    fn attr(self, name: &str) -> Result<WriteReference<'a>> {
         self.and_then(|reference| reference.attr(name))
     }
 
-    fn item(self, index: usize) -> Result<WriteReference<'g>> {
+    fn item(self, index: usize) -> Result<WriteReference<'a>> {
         self.and_then(|reference| reference.item(index))
     }
 }
@@ -1,18 +1,104 @@
 pub mod accessor;
+pub mod allocator;
+pub mod binary;
 pub mod class;
+pub mod dynamic;
 pub mod error;
+pub mod format;
 pub mod instance;
+pub mod migration;
+pub mod native;
+pub mod numeric;
+pub mod path;
+pub mod registry;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod serde;
 
 #[cfg(test)]
 mod tests {
     use crate::accessor::{Accessor, Cast, IntoAccessor, MutableCast};
+    use crate::allocator::{Allocator, Global};
+    use crate::binary;
     use crate::class::array::Array;
+    use crate::class::child::{Child, Key};
+    use crate::class::columnar::Columnar;
+    use crate::class::enumeration::{self, Enum};
+    use crate::class::id::Id;
+    use crate::class::lens::Lens;
+    use crate::class::list::List;
     use crate::class::object::{Builder, Object};
+    use crate::class::optional::Optional;
     use crate::class::value::Value;
-    use crate::class::Class;
-    use crate::instance::Instance;
+    use crate::class::view::View;
+    use crate::class::{Class, Metaclass, Operation, Unique};
+    use crate::dynamic::DynValue;
+    use crate::error::Error;
+    use crate::format::Printers;
+    use crate::instance::read::ReadReference;
+    use crate::instance::write::WriteReference;
+    use crate::instance::{Cloneable, Comparable, Instance};
+    use crate::migration::{Migration, Report};
+    use crate::native::Objective;
+    use crate::path::{Path, Segment};
+    use crate::registry::Registry;
+    use crate::schema::Schema;
+    use std::alloc::Layout;
+    use std::any::TypeId;
+    use std::cell::{Cell, RefCell};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
     use std::sync::Arc;
 
+    thread_local! {
+        static CONSTRUCTED: Cell<usize> = const { Cell::new(0) };
+        static DESTROYED: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Records its construction index on drop so tests can check destroy order.
+    struct Tracked(usize);
+
+    impl Default for Tracked {
+        fn default() -> Self {
+            Tracked(CONSTRUCTED.with(|constructed| constructed.replace(constructed.get() + 1)))
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            DESTROYED.with(|destroyed| destroyed.borrow_mut().push(self.0));
+        }
+    }
+
+    thread_local! {
+        static FAIL_AT: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    /// A tracked value whose construction panics once, when `fail_at` tracked values exist.
+    struct Fragile(Tracked);
+
+    impl Default for Fragile {
+        fn default() -> Self {
+            if FAIL_AT.with(|fail_at| fail_at.get()) == Some(constructed()) {
+                FAIL_AT.with(|fail_at| fail_at.set(None));
+                panic!("construct fragile value");
+            }
+            Fragile(Tracked::default())
+        }
+    }
+
+    fn fail_at(count: usize) {
+        FAIL_AT.with(|fail_at| fail_at.set(Some(count)));
+    }
+
+    fn constructed() -> usize {
+        CONSTRUCTED.with(|constructed| constructed.get())
+    }
+
+    fn destroyed() -> Vec<usize> {
+        DESTROYED.with(|destroyed| destroyed.take())
+    }
+
     #[test]
     fn it_works() {
         let class: Arc<dyn Class> = Arc::new(Value::<u64>::new());
@@ -29,13 +115,13 @@ mod tests {
         let i32_class: Arc<dyn Class> = Arc::new(Value::<i32>::new());
 
         let mut builder = Builder::new("Foo".into());
-        builder.add("a".into(), u64_class.clone());
-        builder.add("b".into(), i32_class.clone());
-        builder.add("c".into(), i32_class.clone());
+        builder.add("a".into(), u64_class.clone()).unwrap();
+        builder.add("b".into(), i32_class.clone()).unwrap();
+        builder.add("c".into(), i32_class.clone()).unwrap();
         assert_eq!(builder.size, 16);
 
         let foo_class = Arc::new(Object::new(builder));
-        let foo = Arc::new(Instance::new(foo_class.clone()));
+        let foo = Instance::new(foo_class.clone());
 
         assert_eq!(
             *foo.read()
@@ -63,7 +149,7 @@ mod tests {
         );
 
         {
-            let write = foo.write().unwrap();
+            let mut write = foo.write().unwrap();
             *write.attr("b").unwrap().cast::<i32>().unwrap() = -69;
             *write.attr("c").unwrap().cast::<i32>().unwrap() = -420;
         }
@@ -85,8 +171,8 @@ mod tests {
             -420
         );
 
-        let foo_array_class: Arc<dyn Class> = Arc::new(Array::new(foo_class.clone(), 3));
-        let foo_array = Arc::new(Instance::new(foo_array_class));
+        let foo_array_class: Arc<dyn Class> = Arc::new(Array::new(foo_class.clone(), 3).unwrap());
+        let foo_array = Instance::new(foo_array_class);
 
         assert_eq!(
             *foo_array
@@ -135,4 +221,2262 @@ mod tests {
             300
         );
     }
+
+    #[test]
+    fn value_destroyed_on_drop() {
+        let class: Arc<dyn Class> = Arc::new(Value::<Tracked>::new());
+        let instance = Instance::new(class);
+        assert_eq!(constructed(), 1);
+        assert!(destroyed().is_empty());
+        drop(instance);
+        assert_eq!(destroyed(), vec![0]);
+    }
+
+    #[test]
+    fn object_members_destroyed_in_reverse() {
+        let tracked_class: Arc<dyn Class> = Arc::new(Value::<Tracked>::new());
+        let string_class: Arc<dyn Class> = Arc::new(Value::<String>::new());
+
+        let mut builder = Builder::new("Foo".into());
+        builder.add("a".into(), tracked_class.clone()).unwrap();
+        builder.add("b".into(), string_class.clone()).unwrap();
+        builder.add("c".into(), tracked_class.clone()).unwrap();
+        let foo_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let foo = Instance::new(foo_class);
+        foo.write()
+            .unwrap()
+            .attr("b")
+            .unwrap()
+            .cast::<String>()
+            .unwrap()
+            .push_str("heap allocated");
+        drop(foo);
+        assert_eq!(destroyed(), vec![1, 0]);
+    }
+
+    #[test]
+    fn nested_members_destroyed_in_reverse() {
+        let tracked_class: Arc<dyn Class> = Arc::new(Value::<Tracked>::new());
+
+        let mut builder = Builder::new("Inner".into());
+        builder.add("a".into(), tracked_class.clone()).unwrap();
+        builder.add("b".into(), tracked_class.clone()).unwrap();
+        let inner_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let mut builder = Builder::new("Outer".into());
+        builder.add("x".into(), tracked_class.clone()).unwrap();
+        builder.add("inner".into(), inner_class.clone()).unwrap();
+        builder
+            .add(
+                "inners".into(),
+                Arc::new(Array::new(inner_class.clone(), 2).unwrap()),
+            )
+            .unwrap();
+        builder
+            .add(
+                "xs".into(),
+                Arc::new(Array::new(tracked_class.clone(), 3).unwrap()),
+            )
+            .unwrap();
+        let outer_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let outer = Instance::new(outer_class);
+        assert_eq!(constructed(), 10);
+        drop(outer);
+        assert_eq!(destroyed(), (0..10).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn poisoned_instance_destroyed_on_drop() {
+        let tracked_class: Arc<dyn Class> = Arc::new(Value::<Tracked>::new());
+        let array_class: Arc<dyn Class> = Arc::new(Array::new(tracked_class, 4).unwrap());
+        let instance = Instance::new(array_class);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = instance.write().unwrap();
+            panic!("poison the instance lock");
+        }));
+        assert!(result.is_err());
+        assert!(instance.read().is_err());
+
+        drop(instance);
+        assert_eq!(destroyed(), vec![3, 2, 1, 0]);
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Pair {
+        a: u64,
+        b: u8,
+    }
+
+    #[repr(C, align(32))]
+    #[derive(Default)]
+    struct Wide {
+        value: u8,
+    }
+
+    #[repr(C)]
+    struct Mixed {
+        x: u8,
+        pairs: [Pair; 3],
+        y: u16,
+        wides: [Wide; 2],
+        z: u8,
+    }
+
+    #[test]
+    fn object_layout_matches_repr_c() {
+        let u64_class: Arc<dyn Class> = Arc::new(Value::<u64>::new());
+        let u8_class: Arc<dyn Class> = Arc::new(Value::<u8>::new());
+
+        let mut builder = Builder::new("Pair".into());
+        builder.add("a".into(), u64_class.clone()).unwrap();
+        builder.add("b".into(), u8_class.clone()).unwrap();
+        assert_eq!(builder.size, 9);
+        let pair_class = Arc::new(Object::new(builder));
+
+        assert_eq!(pair_class.layout(), std::alloc::Layout::new::<Pair>());
+        assert_eq!(pair_class.stride(), std::mem::size_of::<Pair>());
+        assert_eq!(
+            pair_class.attr("b").unwrap().offset,
+            std::mem::offset_of!(Pair, b)
+        );
+    }
+
+    #[test]
+    fn array_layout_matches_repr_c() {
+        let u64_class: Arc<dyn Class> = Arc::new(Value::<u64>::new());
+        let u8_class: Arc<dyn Class> = Arc::new(Value::<u8>::new());
+        let u16_class: Arc<dyn Class> = Arc::new(Value::<u16>::new());
+        let wide_class: Arc<dyn Class> = Arc::new(Value::<Wide>::new());
+
+        let mut builder = Builder::new("Pair".into());
+        builder.add("a".into(), u64_class.clone()).unwrap();
+        builder.add("b".into(), u8_class.clone()).unwrap();
+        let pair_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let pairs_class = Arc::new(Array::new(pair_class.clone(), 3).unwrap());
+        assert_eq!(pairs_class.layout(), std::alloc::Layout::new::<[Pair; 3]>());
+        assert_eq!(
+            pairs_class.item(2).unwrap().offset,
+            2 * std::mem::size_of::<Pair>()
+        );
+
+        let wides_class = Arc::new(Array::new(wide_class.clone(), 2).unwrap());
+        assert_eq!(wides_class.layout(), std::alloc::Layout::new::<[Wide; 2]>());
+
+        let mut builder = Builder::new("Mixed".into());
+        builder.add("x".into(), u8_class.clone()).unwrap();
+        builder.add("pairs".into(), pairs_class.clone()).unwrap();
+        builder.add("y".into(), u16_class.clone()).unwrap();
+        builder.add("wides".into(), wides_class.clone()).unwrap();
+        builder.add("z".into(), u8_class.clone()).unwrap();
+        let mixed_class = Arc::new(Object::new(builder));
+
+        assert_eq!(mixed_class.layout(), std::alloc::Layout::new::<Mixed>());
+        assert_eq!(
+            mixed_class.attr("pairs").item(1).attr("b").unwrap().offset,
+            std::mem::offset_of!(Mixed, pairs) + std::mem::size_of::<Pair>() + 8
+        );
+        assert_eq!(
+            mixed_class.attr("y").unwrap().offset,
+            std::mem::offset_of!(Mixed, y)
+        );
+        assert_eq!(
+            mixed_class.attr("wides").item(1).unwrap().offset,
+            std::mem::offset_of!(Mixed, wides) + std::mem::size_of::<Wide>()
+        );
+        assert_eq!(
+            mixed_class.attr("z").unwrap().offset,
+            std::mem::offset_of!(Mixed, z)
+        );
+
+        let mixed_array_class: Arc<dyn Class> =
+            Arc::new(Array::new(mixed_class.clone(), 2).unwrap());
+        let mixed_array = Instance::new(mixed_array_class);
+        {
+            let mut write = mixed_array.write().unwrap();
+            *write
+                .item(1)
+                .attr("wides")
+                .item(1)
+                .unwrap()
+                .cast::<Wide>()
+                .unwrap() = Wide { value: 7 };
+            *write.item(1).attr("z").unwrap().cast::<u8>().unwrap() = 9;
+        }
+        let read = mixed_array.read().unwrap();
+        assert_eq!(
+            read.item(1)
+                .attr("wides")
+                .item(1)
+                .unwrap()
+                .cast::<Wide>()
+                .unwrap()
+                .value,
+            7
+        );
+        assert_eq!(*read.item(1).attr("z").unwrap().cast::<u8>().unwrap(), 9);
+        assert_eq!(*read.item(0).attr("z").unwrap().cast::<u8>().unwrap(), 0);
+    }
+
+    #[test]
+    fn oversized_layouts() {
+        let u8_class: Arc<dyn Class> = Arc::new(Value::<u8>::new());
+        let u64_class: Arc<dyn Class> = Arc::new(Value::<u64>::new());
+        assert!(matches!(
+            Array::new(u64_class.clone(), usize::MAX / 4),
+            Err(Error::TypeError(_))
+        ));
+        assert!(Array::new(u8_class.clone(), isize::MAX as usize + 1).is_err());
+
+        let half: Arc<dyn Class> = Arc::new(Array::new(u8_class.clone(), usize::MAX / 4).unwrap());
+        let mut builder = Builder::new("Huge".into());
+        builder.add("a".into(), half.clone()).unwrap();
+        builder.add("b".into(), half.clone()).unwrap();
+        builder.add("c".into(), u8_class.clone()).unwrap();
+        assert_eq!(builder.size, isize::MAX as usize);
+        assert!(matches!(
+            builder.add("d".into(), u8_class.clone()),
+            Err(Error::TypeError(_))
+        ));
+        assert!(builder.add("e".into(), half).is_err());
+        assert_eq!(Object::new(builder).size, isize::MAX as usize);
+
+        let mut builder = Builder::new("Pair".into());
+        builder.add("a".into(), u64_class).unwrap();
+        builder.add("b".into(), u8_class).unwrap();
+        let pair = Arc::new(Object::new(builder));
+        // Each column fits on its own but not all of them together
+        let length = isize::MAX as usize / 8;
+        assert!(Array::new(u64::class(), length).is_ok());
+        assert!(matches!(
+            Columnar::new(pair, length),
+            Err(Error::TypeError(_))
+        ));
+    }
+
+    #[test]
+    fn empty_layouts() {
+        let empty_class: Arc<dyn Class> = Arc::new(Object::new(Builder::new("Empty".into())));
+        assert_eq!(empty_class.layout(), std::alloc::Layout::new::<()>());
+        drop(Instance::new(empty_class.clone()));
+        drop(Instance::new(Arc::new(Array::new(empty_class, 4).unwrap())));
+    }
+
+    #[test]
+    fn list_push_pop_insert_remove() {
+        let i32_class: Arc<dyn Class> = Arc::new(Value::<i32>::new());
+        let list_class: Arc<dyn Class> = Arc::new(List::new(i32_class));
+        let list = Instance::new(list_class);
+
+        {
+            let mut write = list.write().unwrap();
+            let mut reference = WriteReference::of(&mut write);
+            assert!(reference.is_empty().unwrap());
+            for i in 0..10 {
+                *reference.push().unwrap().cast::<i32>().unwrap() = i;
+            }
+            *reference.insert(0).unwrap().cast::<i32>().unwrap() = -1;
+            reference.remove(5).unwrap();
+            reference.pop().unwrap();
+            assert_eq!(reference.len().unwrap(), 9);
+            assert!(reference.remove(9).is_err());
+            assert!(reference.insert(10).is_err());
+        }
+
+        let read = list.read().unwrap();
+        let values: Vec<i32> = (0..ReadReference::of(&read).len().unwrap())
+            .map(|i| *read.item(i).unwrap().cast::<i32>().unwrap())
+            .collect();
+        assert_eq!(values, vec![-1, 0, 1, 2, 3, 5, 6, 7, 8]);
+        assert!(read.item(9).is_err());
+    }
+
+    #[test]
+    fn list_of_objects_in_object() {
+        let tracked_class: Arc<dyn Class> = Arc::new(Value::<Tracked>::new());
+        let u64_class: Arc<dyn Class> = Arc::new(Value::<u64>::new());
+
+        let mut builder = Builder::new("Point".into());
+        builder
+            .add("tracked".into(), tracked_class.clone())
+            .unwrap();
+        builder.add("x".into(), u64_class.clone()).unwrap();
+        let point_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let mut builder = Builder::new("Path".into());
+        builder
+            .add("points".into(), Arc::new(List::new(point_class)))
+            .unwrap();
+        let path_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+        let path = Instance::new(path_class);
+
+        {
+            let mut write = path.write().unwrap();
+            let mut points = write.attr("points").unwrap();
+            for x in 0..5 {
+                *points.push().attr("x").unwrap().cast::<u64>().unwrap() = x * 10;
+            }
+            assert_eq!(constructed(), 5);
+            assert!(destroyed().is_empty());
+
+            points.remove(1).unwrap();
+            assert_eq!(destroyed(), vec![1]);
+            points.pop().unwrap();
+            assert_eq!(destroyed(), vec![4]);
+        }
+
+        {
+            let read = path.read().unwrap();
+            assert_eq!(read.attr("points").unwrap().len().unwrap(), 3);
+            assert_eq!(
+                *read
+                    .attr("points")
+                    .item(2)
+                    .attr("x")
+                    .unwrap()
+                    .cast::<u64>()
+                    .unwrap(),
+                30
+            );
+            assert!(read.attr("points").item(3).is_err());
+        }
+
+        drop(path);
+        assert_eq!(destroyed(), vec![3, 2, 0]);
+    }
+
+    #[test]
+    fn list_insert_unwinds() {
+        fail_at(2);
+        let list_class: Arc<dyn Class> = Arc::new(List::new(Arc::new(Value::<Fragile>::new())));
+        let list = Instance::new(list_class);
+        {
+            let mut write = list.write().unwrap();
+            let mut reference = WriteReference::of(&mut write);
+            reference.push().unwrap();
+            reference.push().unwrap();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                reference.insert(0).unwrap();
+            }));
+            assert!(result.is_err());
+            assert_eq!(reference.len().unwrap(), 2);
+            reference.insert(1).unwrap();
+        }
+        {
+            let read = list.read().unwrap();
+            let items: Vec<usize> = (0..3)
+                .map(|i| read.item(i).unwrap().cast::<Fragile>().unwrap().0 .0)
+                .collect();
+            assert_eq!(items, [0, 2, 1]);
+        }
+        drop(list);
+        assert_eq!(destroyed(), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn enum_variant_access() {
+        let i32_class: Arc<dyn Class> = Arc::new(Value::<i32>::new());
+        let tracked_class: Arc<dyn Class> = Arc::new(Value::<Tracked>::new());
+
+        let mut builder = Builder::new("Point".into());
+        builder.add("x".into(), i32_class.clone()).unwrap();
+        builder.add("y".into(), i32_class.clone()).unwrap();
+        let point_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let mut builder = enumeration::Builder::new("Shape".into());
+        builder.add("empty".into(), None).unwrap();
+        builder.add("point".into(), Some(point_class)).unwrap();
+        builder.add("tracked".into(), Some(tracked_class)).unwrap();
+        let shape_class = Arc::new(Enum::new(builder).unwrap());
+        assert!(matches!(
+            Enum::new(enumeration::Builder::new("Never".into())),
+            Err(Error::TypeError(_))
+        ));
+        assert_eq!(shape_class.offset, 8);
+        assert_eq!(shape_class.size, 16);
+        assert!(shape_class.attr("point").is_err());
+
+        let shape = Instance::new(shape_class);
+        {
+            let mut write = shape.write().unwrap();
+            let mut reference = WriteReference::of(&mut write);
+            assert_eq!(reference.variant().unwrap(), "empty");
+            assert!(reference.reborrow().attr("empty").is_err());
+            assert!(reference.reborrow().attr("point").is_err());
+            assert!(reference.reborrow().attr("missing").is_err());
+
+            reference.switch("point").unwrap();
+            *reference
+                .reborrow()
+                .attr("point")
+                .attr("y")
+                .unwrap()
+                .cast::<i32>()
+                .unwrap() = 42;
+            assert!(reference.switch("missing").is_err());
+        }
+        {
+            let read = shape.read().unwrap();
+            assert_eq!(ReadReference::of(&read).variant().unwrap(), "point");
+            assert_eq!(
+                *read.attr("point").attr("y").unwrap().cast::<i32>().unwrap(),
+                42
+            );
+            assert!(read.attr("tracked").is_err());
+        }
+        {
+            let mut write = shape.write().unwrap();
+            let mut reference = WriteReference::of(&mut write);
+            reference.switch("tracked").unwrap();
+            assert_eq!(
+                reference
+                    .reborrow()
+                    .attr("tracked")
+                    .unwrap()
+                    .cast::<Tracked>()
+                    .unwrap()
+                    .0,
+                0
+            );
+            reference.switch("tracked").unwrap();
+            assert_eq!(destroyed(), vec![0]);
+        }
+
+        drop(shape);
+        assert_eq!(destroyed(), vec![1]);
+    }
+
+    #[test]
+    fn enum_switch_unwinds() {
+        fail_at(1);
+        let mut builder = enumeration::Builder::new("State".into());
+        builder
+            .add("tracked".into(), Some(Arc::new(Value::<Tracked>::new())))
+            .unwrap();
+        builder.add("none".into(), None).unwrap();
+        builder
+            .add("failing".into(), Some(Arc::new(Value::<Fragile>::new())))
+            .unwrap();
+        let state = Instance::new(Arc::new(Enum::new(builder).unwrap()));
+        {
+            let mut write = state.write().unwrap();
+            let mut reference = WriteReference::of(&mut write);
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                reference.switch("failing").unwrap();
+            }));
+            assert!(result.is_err());
+            assert_eq!(destroyed(), vec![0]);
+            // The payload never came to exist, so the enum falls back to a variant without one
+            assert_eq!(reference.variant().unwrap(), "none");
+        }
+        drop(state);
+        assert!(destroyed().is_empty());
+    }
+
+    #[test]
+    fn optional_set_and_clear() {
+        let tracked_class: Arc<dyn Class> = Arc::new(Value::<Tracked>::new());
+        let u64_class: Arc<dyn Class> = Arc::new(Value::<u64>::new());
+
+        let mut builder = Builder::new("Foo".into());
+        builder
+            .add("tracked".into(), tracked_class.clone())
+            .unwrap();
+        builder
+            .add("values".into(), Arc::new(Array::new(u64_class, 2).unwrap()))
+            .unwrap();
+        let foo_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let maybe_foo_class = Arc::new(Optional::new(foo_class));
+        assert_eq!(maybe_foo_class.offset, 8);
+        assert_eq!(maybe_foo_class.size, 32);
+
+        let maybe_foo = Instance::new(maybe_foo_class);
+        assert_eq!(constructed(), 0);
+        {
+            let mut write = maybe_foo.write().unwrap();
+            let mut reference = WriteReference::of(&mut write);
+            assert!(!reference.is_set().unwrap());
+            assert!(reference.reborrow().attr("tracked").is_err());
+            assert!(reference.reborrow().item(0).is_err());
+            assert!(reference.get().is_err());
+
+            *reference
+                .set()
+                .attr("values")
+                .item(1)
+                .unwrap()
+                .cast::<u64>()
+                .unwrap() = 7;
+            assert_eq!(constructed(), 1);
+            reference.set().unwrap();
+            assert_eq!(constructed(), 1);
+        }
+        {
+            let read = maybe_foo.read().unwrap();
+            assert!(ReadReference::of(&read).is_set().unwrap());
+            assert_eq!(
+                *read.attr("values").item(1).unwrap().cast::<u64>().unwrap(),
+                7
+            );
+        }
+        {
+            let mut write = maybe_foo.write().unwrap();
+            let mut reference = WriteReference::of(&mut write);
+            reference.clear().unwrap();
+            assert_eq!(destroyed(), vec![0]);
+            assert!(!reference.is_set().unwrap());
+            assert!(reference.reborrow().attr("values").is_err());
+            reference.set().unwrap();
+        }
+
+        drop(maybe_foo);
+        assert_eq!(destroyed(), vec![1]);
+    }
+
+    #[test]
+    fn path_parsing() {
+        let path = Path::parse("a.b[2].c[10][0]").unwrap();
+        assert_eq!(
+            path.segments,
+            vec![
+                Segment::Attr("a".into()),
+                Segment::Attr("b".into()),
+                Segment::Item(2),
+                Segment::Attr("c".into()),
+                Segment::Item(10),
+                Segment::Item(0),
+            ]
+        );
+        assert_eq!(path.to_string(), "a.b[2].c[10][0]");
+        assert_eq!(Path::parse("[3].x").unwrap().to_string(), "[3].x");
+        assert!(Path::parse("").unwrap().segments.is_empty());
+
+        for (source, column) in [
+            ("a..b", 3),
+            ("a.b[x]", 5),
+            ("a[1", 4),
+            ("a b", 2),
+            ("a.", 3),
+        ] {
+            match Path::parse(source) {
+                Err(Error::SyntaxError(message)) => {
+                    assert!(
+                        message.contains(&format!("column {}", column)),
+                        "{}",
+                        message
+                    );
+                    assert!(message.ends_with(&format!("\n  {}^", " ".repeat(column - 1))));
+                }
+                _ => panic!("expected syntax error for {}", source),
+            }
+        }
+    }
+
+    #[test]
+    fn path_resolution() {
+        let i32_class: Arc<dyn Class> = Arc::new(Value::<i32>::new());
+
+        let mut builder = Builder::new("Inner".into());
+        builder.add("x".into(), i32_class.clone()).unwrap();
+        builder.add("y".into(), i32_class.clone()).unwrap();
+        let inner_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let mut builder = Builder::new("Outer".into());
+        builder.add("flag".into(), i32_class.clone()).unwrap();
+        builder
+            .add(
+                "inners".into(),
+                Arc::new(Array::new(inner_class, 3).unwrap()),
+            )
+            .unwrap();
+        let outer_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let path: Path = "inners[2].y".parse().unwrap();
+        assert_eq!(path.view(outer_class.clone()).unwrap().offset, 24);
+
+        let outer = Instance::new(outer_class.clone());
+        *outer
+            .write()
+            .unwrap()
+            .path(&path)
+            .unwrap()
+            .cast::<i32>()
+            .unwrap() = 5;
+        let read = outer.read().unwrap();
+        assert_eq!(*read.path(&path).unwrap().cast::<i32>().unwrap(), 5);
+
+        match read.path(&Path::parse("inners[1].z").unwrap()) {
+            Err(Error::AttributeError(message)) => {
+                assert!(message.contains("resolved \"inners[1]\""), "{}", message)
+            }
+            _ => panic!("expected attribute error"),
+        }
+        assert!(matches!(
+            Path::parse("inners[3]").unwrap().view(outer_class),
+            Err(Error::IndexError(_))
+        ));
+    }
+
+    #[test]
+    fn typed_view_access() {
+        let f32_class: Arc<dyn Class> = Arc::new(Value::<f32>::new());
+
+        let mut builder = Builder::new("Particle".into());
+        builder.add("x".into(), f32_class.clone()).unwrap();
+        builder.add("y".into(), f32_class.clone()).unwrap();
+        let particle_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+        let particles_class: Arc<dyn Class> = Arc::new(Array::new(particle_class, 4).unwrap());
+
+        let y = particles_class
+            .item(3)
+            .attr("y")
+            .unwrap()
+            .typed::<f32>()
+            .unwrap();
+        assert_eq!(y.offset(), 28);
+        assert!(particles_class.item(3).unwrap().typed::<f32>().is_err());
+        assert!(particles_class
+            .item(3)
+            .attr("y")
+            .unwrap()
+            .typed::<f64>()
+            .is_err());
+
+        let particles = Instance::new(particles_class);
+        *y.get_mut(&mut particles.write().unwrap()) += 1.5;
+        *y.get_mut(&mut particles.write().unwrap()) *= 2.0;
+        let read = particles.read().unwrap();
+        assert_eq!(*y.get(&read), 3.0);
+        assert_eq!(*read.item(3).attr("y").unwrap().cast::<f32>().unwrap(), 3.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn typed_view_rejects_other_class() {
+        let f32_class: Arc<dyn Class> = Arc::new(Value::<f32>::new());
+        let view = View::of(f32_class.clone()).typed::<f32>().unwrap();
+        let other = Instance::new(Arc::new(Value::<u32>::new()));
+        view.get(&other.read().unwrap());
+    }
+
+    #[test]
+    fn dynamic_values() {
+        let u8_class: Arc<dyn Class> = Arc::new(Value::<u8>::new());
+        let i16_class: Arc<dyn Class> = Arc::new(Value::<i16>::new());
+        let f32_class: Arc<dyn Class> = Arc::new(Value::<f32>::new());
+        let string_class: Arc<dyn Class> = Arc::new(Value::<String>::new());
+        let bool_class: Arc<dyn Class> = Arc::new(Value::<bool>::new());
+
+        let mut builder = enumeration::Builder::new("Status".into());
+        builder.add("idle".into(), None).unwrap();
+        builder.add("busy".into(), Some(u8_class.clone())).unwrap();
+        let status_class: Arc<dyn Class> = Arc::new(Enum::new(builder).unwrap());
+
+        let mut builder = Builder::new("Tag".into());
+        builder.add("name".into(), string_class.clone()).unwrap();
+        builder.add("on".into(), bool_class.clone()).unwrap();
+        let tag_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let mut builder = Builder::new("Record".into());
+        builder.add("small".into(), u8_class.clone()).unwrap();
+        builder
+            .add(
+                "pair".into(),
+                Arc::new(Array::new(i16_class.clone(), 2).unwrap()),
+            )
+            .unwrap();
+        builder.add("ratio".into(), f32_class.clone()).unwrap();
+        builder
+            .add("tags".into(), Arc::new(List::new(tag_class)))
+            .unwrap();
+        builder
+            .add("maybe".into(), Arc::new(Optional::new(i16_class.clone())))
+            .unwrap();
+        builder.add("status".into(), status_class).unwrap();
+        let record_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+        let record = Instance::new(record_class);
+
+        let map = |entries: Vec<(&str, DynValue)>| {
+            DynValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            )
+        };
+
+        let read = record.read().unwrap();
+        assert_eq!(
+            ReadReference::of(&read).get_dynamic().unwrap(),
+            map(vec![
+                ("small", DynValue::UInt(0)),
+                (
+                    "pair",
+                    DynValue::List(vec![DynValue::Int(0), DynValue::Int(0)])
+                ),
+                ("ratio", DynValue::Float(0.0)),
+                ("tags", DynValue::List(vec![])),
+                ("maybe", DynValue::Null),
+                ("status", map(vec![("idle", DynValue::Null)])),
+            ])
+        );
+        drop(read);
+
+        let updated = map(vec![
+            ("small", DynValue::UInt(200)),
+            (
+                "pair",
+                DynValue::List(vec![DynValue::Int(-3), DynValue::UInt(4)]),
+            ),
+            ("ratio", DynValue::Float(0.5)),
+            (
+                "tags",
+                DynValue::List(vec![map(vec![
+                    ("name", DynValue::String("hello".into())),
+                    ("on", DynValue::Bool(true)),
+                ])]),
+            ),
+            ("maybe", DynValue::Int(7)),
+            ("status", map(vec![("busy", DynValue::UInt(3))])),
+        ]);
+        let mut write = record.write().unwrap();
+        let mut reference = WriteReference::of(&mut write);
+        reference.set_dynamic(&updated).unwrap();
+        assert_eq!(
+            *reference
+                .reborrow()
+                .attr("tags")
+                .item(0)
+                .attr("name")
+                .unwrap()
+                .cast::<String>()
+                .unwrap(),
+            "hello"
+        );
+        assert_eq!(
+            reference.get_dynamic().unwrap(),
+            map(vec![
+                ("small", DynValue::UInt(200)),
+                (
+                    "pair",
+                    DynValue::List(vec![DynValue::Int(-3), DynValue::Int(4)])
+                ),
+                ("ratio", DynValue::Float(0.5)),
+                (
+                    "tags",
+                    DynValue::List(vec![map(vec![
+                        ("name", DynValue::String("hello".into())),
+                        ("on", DynValue::Bool(true)),
+                    ])]),
+                ),
+                ("maybe", DynValue::Int(7)),
+                ("status", map(vec![("busy", DynValue::UInt(3))])),
+            ])
+        );
+
+        let mut small = reference.reborrow().attr("small").unwrap();
+        assert!(matches!(
+            small.set_dynamic(&DynValue::Int(256)),
+            Err(Error::ValueError(_))
+        ));
+        assert!(matches!(
+            small.set_dynamic(&DynValue::Int(-1)),
+            Err(Error::ValueError(_))
+        ));
+        assert!(matches!(
+            small.set_dynamic(&DynValue::Bool(true)),
+            Err(Error::TypeError(_))
+        ));
+        assert!(reference
+            .reborrow()
+            .attr("pair")
+            .unwrap()
+            .set_dynamic(&DynValue::List(vec![DynValue::Int(1)]))
+            .is_err());
+        assert!(reference
+            .set_dynamic(&map(vec![("missing", DynValue::Null)]))
+            .is_err());
+    }
+
+    #[test]
+    fn numeric_coercion() {
+        let i32_class: Arc<dyn Class> = Arc::new(Value::<i32>::new());
+        let u8_class: Arc<dyn Class> = Arc::new(Value::<u8>::new());
+        let f32_class: Arc<dyn Class> = Arc::new(Value::<f32>::new());
+        let string_class: Arc<dyn Class> = Arc::new(Value::<String>::new());
+
+        let mut builder = Builder::new("Numbers".into());
+        builder.add("int".into(), i32_class).unwrap();
+        builder.add("byte".into(), u8_class).unwrap();
+        builder.add("float".into(), f32_class).unwrap();
+        builder.add("string".into(), string_class).unwrap();
+        builder
+            .add("wide".into(), Arc::new(Value::<i128>::new()))
+            .unwrap();
+        builder
+            .add("huge".into(), Arc::new(Value::<u128>::new()))
+            .unwrap();
+        let numbers = Instance::new(Arc::new(Object::new(builder)));
+
+        let mut write = numbers.write().unwrap();
+        let mut int = write.attr("int").unwrap();
+        int.write_from(-7i64).unwrap();
+        assert_eq!(int.read_as::<i64>().unwrap(), -7);
+        assert_eq!(int.read_as::<f64>().unwrap(), -7.0);
+        assert!(matches!(int.read_as::<u32>(), Err(Error::ValueError(_))));
+        assert!(matches!(
+            int.write_from(u64::MAX),
+            Err(Error::ValueError(_))
+        ));
+        assert_eq!(*int.cast::<i32>().unwrap(), -7);
+
+        let mut byte = write.attr("byte").unwrap();
+        byte.write_from(255i32).unwrap();
+        assert!(byte.read_as::<i8>().is_err());
+        assert_eq!(byte.read_as::<u64>().unwrap(), 255);
+        assert!(byte.write_from(256u16).is_err());
+        byte.write_from(12.0f64).unwrap();
+        assert_eq!(*byte.cast::<u8>().unwrap(), 12);
+        assert!(byte.write_from(1.5f32).is_err());
+
+        let mut float = write.attr("float").unwrap();
+        float.write_from(0.25f64).unwrap();
+        assert_eq!(float.read_as::<f64>().unwrap(), 0.25);
+        assert!(float.write_from(1e300f64).is_err());
+        assert!(float.write_from(u64::MAX - 1).is_err());
+        float.write_from(16777216u32).unwrap();
+        assert_eq!(float.read_as::<u32>().unwrap(), 16777216);
+
+        let mut string = write.attr("string").unwrap();
+        assert!(matches!(string.read_as::<u8>(), Err(Error::TypeError(_))));
+        assert!(matches!(string.write_from(1u8), Err(Error::TypeError(_))));
+
+        let mut wide = write.attr("wide").unwrap();
+        wide.write_from(i128::MIN).unwrap();
+        assert_eq!(wide.read_as::<i128>().unwrap(), i128::MIN);
+        assert_eq!(wide.read_as::<f64>().unwrap(), -(2f64.powi(127)));
+        assert!(wide.read_as::<i64>().is_err());
+        assert!(wide.write_from(u128::MAX).is_err());
+        wide.write_from(-3.0f32).unwrap();
+        assert_eq!(wide.read_as::<i8>().unwrap(), -3);
+
+        let mut huge = write.attr("huge").unwrap();
+        huge.write_from(u128::MAX).unwrap();
+        assert_eq!(*huge.cast::<u128>().unwrap(), u128::MAX);
+        assert_eq!(huge.read_as::<u128>().unwrap(), u128::MAX);
+        assert!(huge.read_as::<i128>().is_err());
+        assert!(huge.read_as::<f64>().is_err());
+        assert!(huge.write_from(-1i8).is_err());
+        huge.write_from(2f64.powi(127)).unwrap();
+        assert_eq!(huge.read_as::<u128>().unwrap(), 1 << 127);
+        assert_eq!(huge.read_as::<f32>().unwrap(), 2f32.powi(127));
+        assert!(huge.write_from(2f64.powi(128)).is_err());
+    }
+
+    #[test]
+    fn member_reflection() {
+        let u8_class: Arc<dyn Class> = Arc::new(Value::<u8>::new());
+        let u32_class: Arc<dyn Class> = Arc::new(Value::<u32>::new());
+
+        let mut builder = Builder::new("Pixel".into());
+        builder.add("alpha".into(), u8_class.clone()).unwrap();
+        builder
+            .add(
+                "rgb".into(),
+                Arc::new(Array::new(u32_class.clone(), 3).unwrap()),
+            )
+            .unwrap();
+        builder
+            .add("layers".into(), Arc::new(List::new(u8_class.clone())))
+            .unwrap();
+        let pixel_class = Arc::new(Object::new(builder));
+
+        let members: Vec<(&str, usize, usize, usize)> = pixel_class
+            .members()
+            .iter()
+            .map(|member| (&*member.name, member.offset, member.size(), member.align()))
+            .collect();
+        assert_eq!(
+            members,
+            vec![("alpha", 0, 1, 1), ("rgb", 4, 12, 4), ("layers", 16, 24, 8)]
+        );
+        assert_eq!(pixel_class.member("rgb").unwrap().offset, 4);
+        assert!(pixel_class.member("missing").is_none());
+
+        // Walk every statically located leaf through the generic hook
+        fn leaves(class: &dyn Class, offset: usize, path: String, out: &mut Vec<(String, usize)>) {
+            let children = class.children();
+            if children.is_empty() {
+                out.push((path.clone(), offset));
+            }
+            for Child {
+                key,
+                class,
+                offset: child_offset,
+            } in children
+            {
+                let path = match key {
+                    Key::Name(_) if path.is_empty() => key.to_string(),
+                    Key::Name(_) => format!("{}.{}", path, key),
+                    Key::Index(_) => format!("{}{}", path, key),
+                };
+                leaves(&*class, offset + child_offset, path, out);
+            }
+        }
+        let mut out = Vec::new();
+        leaves(&*pixel_class, 0, String::new(), &mut out);
+        assert_eq!(
+            out,
+            vec![
+                ("alpha".to_string(), 0),
+                ("rgb[0]".to_string(), 4),
+                ("rgb[1]".to_string(), 8),
+                ("rgb[2]".to_string(), 12),
+                ("layers".to_string(), 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn inheritance() {
+        let u64_class: Arc<dyn Class> = Arc::new(Value::<u64>::new());
+        let i32_class: Arc<dyn Class> = Arc::new(Value::<i32>::new());
+
+        let mut builder = Builder::new("Base".into());
+        builder.add("a".into(), u64_class.clone()).unwrap();
+        let base_class = Arc::new(Object::new(builder));
+
+        let mut builder = Builder::new_inherit("Derived".into(), base_class.clone());
+        assert!(matches!(
+            builder.add("a".into(), i32_class.clone()),
+            Err(Error::AttributeError(_))
+        ));
+        builder.add("b".into(), i32_class.clone()).unwrap();
+        assert!(builder.add("b".into(), i32_class.clone()).is_err());
+        let derived_class = Arc::new(Object::new(builder));
+
+        let mut builder = Builder::new_inherit("Leaf".into(), derived_class.clone());
+        builder.add("c".into(), i32_class.clone()).unwrap();
+        let leaf_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let mut builder = Builder::new("Base".into());
+        builder.add("a".into(), i32_class.clone()).unwrap();
+        let unrelated_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+        assert!(leaf_class.is_subclass_of(&*derived_class));
+        assert!(leaf_class.is_subclass_of(&*base_class));
+        assert!(leaf_class.is_subclass_of(&*leaf_class));
+        assert!(!base_class.is_subclass_of(&*leaf_class));
+        assert!(!leaf_class.is_subclass_of(&*unrelated_class));
+
+        let a = base_class.attr("a").unwrap();
+        let typed_b = derived_class.attr("b").unwrap().typed::<i32>().unwrap();
+        let leaf = Instance::new(leaf_class.clone());
+        {
+            let mut write = leaf.write().unwrap();
+            *write.through(&a).unwrap().cast::<u64>().unwrap() = 11;
+            *typed_b.get_mut(&mut write) = -2;
+        }
+
+        let read = leaf.read().unwrap();
+        assert_eq!(*read.attr("a").unwrap().cast::<u64>().unwrap(), 11);
+        assert_eq!(*read.attr("b").unwrap().cast::<i32>().unwrap(), -2);
+
+        let base: Arc<dyn Class> = base_class.clone();
+        let upcast = ReadReference::of(&read).upcast(&base).unwrap();
+        assert_eq!(
+            *upcast.clone().attr("a").unwrap().cast::<u64>().unwrap(),
+            11
+        );
+        assert!(upcast.clone().attr("b").is_err());
+        assert!(upcast.upcast(&leaf_class).is_err());
+        assert!(ReadReference::of(&read).upcast(&unrelated_class).is_err());
+
+        let unrelated = Instance::new(unrelated_class);
+        assert!(unrelated.read().unwrap().through(&a).is_err());
+
+        // The leaf class is remembered as compatible, other classes are still checked
+        assert_eq!(*typed_b.get(&read), -2);
+        assert_eq!(*typed_b.clone().get(&read), -2);
+        let unrelated = unrelated.read().unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            typed_b.get(&unrelated);
+        }));
+        assert!(result.is_err());
+        assert_eq!(*typed_b.get(&read), -2);
+    }
+
+    #[test]
+    fn pretty_printing() {
+        let u64_class: Arc<dyn Class> = Arc::new(Value::<u64>::new());
+        let i64_class: Arc<dyn Class> = Arc::new(Value::<i64>::new());
+        let mut builder = Builder::new("Foo".into());
+        builder.add("a".into(), u64_class.clone()).unwrap();
+        builder.add("b".into(), i64_class.clone()).unwrap();
+        builder
+            .add(
+                "c".into(),
+                Arc::new(Array::new(u64_class.clone(), 3).unwrap()),
+            )
+            .unwrap();
+        let foo_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let mut builder = enumeration::Builder::new("Shape".into());
+        builder.add("empty".into(), None).unwrap();
+        builder.add("foo".into(), Some(foo_class.clone())).unwrap();
+        let shape_class = Arc::new(Enum::new(builder).unwrap());
+
+        let mut builder = Builder::new("Bar".into());
+        builder.add("foo".into(), foo_class.clone()).unwrap();
+        builder
+            .add("list".into(), Arc::new(List::new(i64_class.clone())))
+            .unwrap();
+        builder
+            .add("maybe".into(), Arc::new(Optional::new(u64_class.clone())))
+            .unwrap();
+        builder.add("shape".into(), shape_class).unwrap();
+        builder
+            .add("unit".into(), Arc::new(Value::<()>::new()))
+            .unwrap();
+        let bar_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let foo = Instance::new(foo_class);
+        {
+            let mut write = foo.write().unwrap();
+            write.attr("a").unwrap().write_from(69).unwrap();
+            write.attr("b").unwrap().write_from(-69).unwrap();
+            for i in 0..3 {
+                write.attr("c").item(i).unwrap().write_from(i + 1).unwrap();
+            }
+        }
+        assert_eq!(format!("{:?}", foo), "Foo { a: 69, b: -69, c: [1, 2, 3] }");
+        assert_eq!(format!("{}", foo), format!("{:?}", foo));
+        assert_eq!(
+            format!("{:#?}", foo),
+            "Foo {\n    a: 69,\n    b: -69,\n    c: [\n        1,\n        2,\n        3,\n    ],\n}"
+        );
+        {
+            let _write = foo.write().unwrap();
+            assert_eq!(format!("{:?}", foo), "<locked Foo>");
+        }
+
+        let bar = Instance::new(bar_class);
+        assert_eq!(
+            format!("{:?}", bar),
+            "Bar { foo: Foo { a: 0, b: 0, c: [0, 0, 0] }, list: [], maybe: None, \
+             shape: Shape::empty, unit: <()> }"
+        );
+        {
+            let mut write = bar.write().unwrap();
+            write
+                .attr("list")
+                .unwrap()
+                .push()
+                .unwrap()
+                .write_from(-1)
+                .unwrap();
+            write
+                .attr("maybe")
+                .unwrap()
+                .set()
+                .unwrap()
+                .write_from(2)
+                .unwrap();
+            write.attr("shape").unwrap().switch("foo").unwrap();
+        }
+
+        let read = bar.read().unwrap();
+        assert_eq!(
+            format!("{}", read.pretty().depth(1)),
+            "Bar { foo: Foo { .. }, list: [..], maybe: Some(2), shape: Shape::foo(..), unit: <()> }"
+        );
+        assert_eq!(
+            format!("{:?}", read.attr("shape").unwrap().pretty()),
+            "Shape::foo(Foo { a: 0, b: 0, c: [0, 0, 0] })"
+        );
+        assert_eq!(format!("{}", read.attr("list").unwrap().pretty()), "[-1]");
+
+        let mut printers = Printers::default();
+        printers.register_with::<u64>(|value, formatter| write!(formatter, "{:#x}", value));
+        printers.register_with::<()>(|_, formatter| formatter.write_str("unit"));
+        assert_eq!(
+            format!("{}", read.attr("foo").unwrap().pretty().printers(&printers)),
+            "Foo { a: 0x0, b: 0, c: [0x0, 0x0, 0x0] }"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                read.attr("unit").unwrap().pretty().printers(&printers)
+            ),
+            "unit"
+        );
+    }
+
+    #[test]
+    fn clone_eq_hash() {
+        fn hash(instance: &Instance) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            instance.try_hash(&mut hasher).unwrap();
+            hasher.finish()
+        }
+
+        let mut builder = Builder::new("Record".into());
+        builder.add("id".into(), u64::class()).unwrap();
+        builder.add("name".into(), String::class()).unwrap();
+        builder.add("scores".into(), <[i32; 3]>::class()).unwrap();
+        builder
+            .add("tags".into(), Arc::new(List::new(String::class())))
+            .unwrap();
+        builder
+            .add("nickname".into(), Arc::new(Optional::new(String::class())))
+            .unwrap();
+        let mut status = enumeration::Builder::new("Status".into());
+        status.add("idle".into(), None).unwrap();
+        status.add("busy".into(), Some(u32::class())).unwrap();
+        builder
+            .add("status".into(), Arc::new(Enum::new(status).unwrap()))
+            .unwrap();
+        let record_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+        assert!(record_class.supports(Operation::Clone));
+        assert!(record_class.supports(Operation::Eq));
+        assert!(record_class.supports(Operation::Hash));
+
+        let record = Instance::new(record_class.clone());
+        {
+            let mut write = record.write().unwrap();
+            write.attr("id").unwrap().write_from(5).unwrap();
+            *write.attr("name").unwrap().cast::<String>().unwrap() = "five".into();
+            write
+                .attr("scores")
+                .item(2)
+                .unwrap()
+                .write_from(-5)
+                .unwrap();
+            let mut tags = write.attr("tags").unwrap();
+            *tags.push().unwrap().cast::<String>().unwrap() = "new".into();
+            *tags.push().unwrap().cast::<String>().unwrap() = "shiny".into();
+            *write
+                .attr("nickname")
+                .unwrap()
+                .set()
+                .unwrap()
+                .cast::<String>()
+                .unwrap() = "v".into();
+            let mut status = write.attr("status").unwrap();
+            status.switch("busy").unwrap();
+            status.attr("busy").unwrap().write_from(3).unwrap();
+        }
+
+        let copy = record.try_clone().unwrap();
+        assert!(record.try_eq(&copy).unwrap());
+        assert_eq!(hash(&record), hash(&copy));
+        assert_eq!(format!("{:?}", copy), format!("{:?}", record));
+
+        copy.write()
+            .unwrap()
+            .attr("name")
+            .unwrap()
+            .cast::<String>()
+            .unwrap()
+            .push('!');
+        assert!(!record.try_eq(&copy).unwrap());
+        assert_ne!(hash(&record), hash(&copy));
+        let copy = record.try_clone().unwrap();
+        copy.write().unwrap().attr("tags").unwrap().pop().unwrap();
+        assert!(!record.try_eq(&copy).unwrap());
+        let copy = record.try_clone().unwrap();
+        copy.write()
+            .unwrap()
+            .attr("nickname")
+            .unwrap()
+            .clear()
+            .unwrap();
+        assert!(!record.try_eq(&copy).unwrap());
+        assert_ne!(hash(&record), hash(&copy));
+        let copy = record.try_clone().unwrap();
+        copy.write()
+            .unwrap()
+            .attr("status")
+            .unwrap()
+            .switch("idle")
+            .unwrap();
+        assert!(!record.try_eq(&copy).unwrap());
+        assert_eq!(
+            record
+                .read()
+                .unwrap()
+                .attr("name")
+                .unwrap()
+                .cast::<String>()
+                .unwrap(),
+            "five"
+        );
+        assert!(record.try_eq(&record).unwrap());
+        let empty = Instance::new(Arc::new(Object::new(Builder::new("Empty".into()))));
+        assert!(!record.try_eq(&empty).unwrap());
+
+        let mut builder = Builder::new("Point".into());
+        builder.add("x".into(), f32::class()).unwrap();
+        builder
+            .add("raw".into(), Arc::new(Value::<u8>::new().with_clone()))
+            .unwrap();
+        let point_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+        let point = Instance::new(point_class);
+        assert!(point.try_clone().is_ok());
+        assert!(matches!(point.try_eq(&point), Err(Error::TypeError(_))));
+        assert!(matches!(
+            point.try_hash(&mut DefaultHasher::new()),
+            Err(Error::TypeError(_))
+        ));
+
+        let array = Instance::new(Arc::new(
+            Array::new(Arc::new(Value::<u8>::new()), 2).unwrap(),
+        ));
+        assert!(matches!(array.try_clone(), Err(Error::TypeError(_))));
+
+        // Checked wrappers provide the std traits once support has been verified
+        let record = Cloneable::new(record).unwrap();
+        let key = Comparable::new(record.clone().into_inner()).unwrap();
+        let same = Comparable::new(record.try_clone().unwrap()).unwrap();
+        assert!(key == same);
+        assert!(key != Comparable::new(copy).unwrap());
+        let (mut left, mut right) = (DefaultHasher::new(), DefaultHasher::new());
+        std::hash::Hash::hash(&key, &mut left);
+        std::hash::Hash::hash(&same, &mut right);
+        assert_eq!(left.finish(), right.finish());
+        assert_eq!(left.finish(), hash(&record));
+        assert!(matches!(Comparable::new(point), Err(Error::TypeError(_))));
+        assert!(matches!(Cloneable::new(array), Err(Error::TypeError(_))));
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut builder = Builder::new("Base".into());
+        builder.add("id".into(), u64::class()).unwrap();
+        let base_class = Arc::new(Object::new(builder));
+
+        let mut builder = enumeration::Builder::new("Shape".into());
+        builder.add("none".into(), None).unwrap();
+        builder.add("circle".into(), Some(f64::class())).unwrap();
+        let shape_class = Arc::new(Enum::new(builder).unwrap());
+
+        let mut builder = Builder::new_inherit("Derived".into(), base_class.clone());
+        builder.add("shape".into(), shape_class).unwrap();
+        builder.add("name".into(), String::class()).unwrap();
+        builder.add("flags".into(), <[bool; 2]>::class()).unwrap();
+        builder
+            .add("scores".into(), Arc::new(List::new(i16::class())))
+            .unwrap();
+        builder
+            .add("parent".into(), Arc::new(Optional::new(char::class())))
+            .unwrap();
+        builder.add("size".into(), usize::class()).unwrap();
+        let derived_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let instance = Instance::new(derived_class.clone());
+        {
+            let mut write = instance.write().unwrap();
+            write.attr("id").unwrap().write_from(u64::MAX).unwrap();
+            write.attr("shape").unwrap().switch("circle").unwrap();
+            write
+                .attr("shape")
+                .attr("circle")
+                .unwrap()
+                .write_from(2.5)
+                .unwrap();
+            *write.attr("name").unwrap().cast::<String>().unwrap() = "ünïcode".into();
+            *write.attr("flags").item(1).unwrap().cast::<bool>().unwrap() = true;
+            for score in [-1, 300] {
+                write
+                    .attr("scores")
+                    .unwrap()
+                    .push()
+                    .unwrap()
+                    .write_from(score)
+                    .unwrap();
+            }
+            *write
+                .attr("parent")
+                .unwrap()
+                .set()
+                .unwrap()
+                .cast::<char>()
+                .unwrap() = 'p';
+            write.attr("size").unwrap().write_from(42).unwrap();
+        }
+
+        let bytes = binary::encode(&instance.read().unwrap()).unwrap();
+        assert_eq!(bytes[..4], binary::MAGIC);
+        let decoded = binary::decode(&bytes).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", instance));
+        assert_eq!(binary::encode(&decoded.read().unwrap()).unwrap(), bytes);
+
+        let read = decoded.read().unwrap();
+        let class = read.class();
+        assert!(!class.is_subclass_of(&*derived_class));
+        assert_eq!(class.layout(), derived_class.layout());
+        let base = class.base().unwrap();
+        assert_eq!(format!("{:?}", base), "Base");
+        assert!(class.is_subclass_of(&**base));
+        assert!(Arc::ptr_eq(
+            &class.attr("name").unwrap().class,
+            &(String::class() as Arc<dyn Class>)
+        ));
+
+        let empty = Instance::new(Arc::new(Object::new(Builder::new("Empty".into()))));
+        let empty_bytes = binary::encode(&empty.read().unwrap()).unwrap();
+        assert_eq!(
+            format!("{:?}", binary::decode(&empty_bytes).unwrap()),
+            "Empty"
+        );
+    }
+
+    #[test]
+    fn binary_errors() {
+        let mut builder = Builder::new("Foo".into());
+        builder.add("a".into(), u32::class()).unwrap();
+        builder.add("b".into(), bool::class()).unwrap();
+        let foo = Instance::new(Arc::new(Object::new(builder)));
+        let bytes = binary::encode(&foo.read().unwrap()).unwrap();
+
+        for length in 0..bytes.len() {
+            assert!(binary::decode(&bytes[..length]).is_err());
+        }
+        assert!(matches!(
+            binary::decode(&bytes[..bytes.len() - 1]),
+            Err(Error::TruncatedError(_))
+        ));
+        assert!(matches!(
+            binary::decode(b"JSON{}"),
+            Err(Error::CorruptError(_))
+        ));
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(binary::VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            binary::decode(&newer),
+            Err(Error::VersionError(_))
+        ));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            binary::decode(&trailing),
+            Err(Error::CorruptError(_))
+        ));
+
+        let mut invalid_bool = bytes.clone();
+        *invalid_bool.last_mut().unwrap() = 2;
+        assert!(matches!(
+            binary::decode(&invalid_bool),
+            Err(Error::CorruptError(_))
+        ));
+
+        let mut unknown_code = bytes.clone();
+        unknown_code[6] = 0xff;
+        assert!(matches!(
+            binary::decode(&unknown_code),
+            Err(Error::CorruptError(_))
+        ));
+
+        let header = || {
+            let mut bytes = binary::MAGIC.to_vec();
+            bytes.extend_from_slice(&binary::VERSION.to_le_bytes());
+            bytes
+        };
+        let mut oversized = header();
+        oversized.push(0x21);
+        oversized.extend_from_slice(&u64::MAX.to_le_bytes());
+        oversized.push(0x0c);
+        assert!(matches!(
+            binary::decode(&oversized),
+            Err(Error::CorruptError(_))
+        ));
+
+        // Lengths the remaining input cannot hold are rejected before anything is allocated
+        let mut huge_array = header();
+        huge_array.push(0x21);
+        huge_array.extend_from_slice(&(1u64 << 62).to_le_bytes());
+        huge_array.push(0x09);
+        assert_eq!(huge_array.len(), 16);
+        assert!(matches!(
+            binary::decode(&huge_array),
+            Err(Error::CorruptError(_))
+        ));
+        let mut huge_list = header();
+        huge_list.extend_from_slice(&[0x22, 0x09]);
+        huge_list.extend_from_slice(&(1u64 << 62).to_le_bytes());
+        assert!(matches!(
+            binary::decode(&huge_list),
+            Err(Error::CorruptError(_))
+        ));
+
+        // Zero-sized elements take no input, so their count is limited instead
+        let empty_object = [
+            [0x20].as_slice(),
+            &0u64.to_le_bytes(),
+            &[0],
+            &0u64.to_le_bytes(),
+        ];
+        let mut empty_array = header();
+        empty_array.push(0x21);
+        empty_array.extend_from_slice(&u64::MAX.to_le_bytes());
+        empty_array.extend(empty_object.concat());
+        assert_eq!(empty_array.len(), 33);
+        assert!(matches!(
+            binary::decode(&empty_array),
+            Err(Error::ValueError(_))
+        ));
+        let mut empty_list = header();
+        empty_list.push(0x22);
+        empty_list.extend(empty_object.concat());
+        empty_list.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            binary::decode(&empty_list),
+            Err(Error::ValueError(_))
+        ));
+
+        let limits = binary::Limits {
+            size: 4,
+            ..Default::default()
+        };
+        assert!(matches!(
+            binary::decode_with_limits(&bytes, limits),
+            Err(Error::ValueError(_))
+        ));
+        let limits = binary::Limits {
+            size: 8,
+            ..Default::default()
+        };
+        assert!(binary::decode_with_limits(&bytes, limits).is_ok());
+
+        let unit = Instance::new(Arc::new(Value::<()>::new()));
+        assert!(matches!(
+            binary::encode(&unit.read().unwrap()),
+            Err(Error::TypeError(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use crate::serde::{Codecs, InstanceSeed, Policy};
+        use serde::de::DeserializeSeed;
+        use serde_json::json;
+
+        fn from_json(seed: InstanceSeed<'_>, json: &str) -> serde_json::Result<Instance> {
+            seed.deserialize(&mut serde_json::Deserializer::from_str(json))
+        }
+
+        let mut builder = enumeration::Builder::new("Shape".into());
+        builder.add("none".into(), None).unwrap();
+        builder.add("circle".into(), Some(f64::class())).unwrap();
+        let shape_class = Arc::new(Enum::new(builder).unwrap());
+
+        let mut builder = Builder::new("Foo".into());
+        builder.add("a".into(), u64::class()).unwrap();
+        builder.add("b".into(), <[i32; 2]>::class()).unwrap();
+        builder
+            .add("c".into(), Arc::new(List::new(String::class())))
+            .unwrap();
+        builder
+            .add("d".into(), Arc::new(Optional::new(bool::class())))
+            .unwrap();
+        builder.add("e".into(), shape_class).unwrap();
+        let foo_class: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let foo = Instance::new(foo_class.clone());
+        assert_eq!(
+            serde_json::to_value(foo.read().unwrap()).unwrap(),
+            json!({"a": 0, "b": [0, 0], "c": [], "d": null, "e": {"none": null}})
+        );
+
+        let text = r#"{"a": 69, "b": [-1, 2], "c": ["x", "y"], "d": true, "e": {"circle": 1.5}}"#;
+        let foo = from_json(InstanceSeed::new(foo_class.clone()), text).unwrap();
+        {
+            let read = foo.read().unwrap();
+            assert_eq!(read.attr("e").unwrap().variant().unwrap(), "circle");
+            assert_eq!(
+                serde_json::to_value(&read).unwrap(),
+                serde_json::from_str::<serde_json::Value>(text).unwrap()
+            );
+            assert_eq!(
+                serde_json::to_string(&read.attr("c").unwrap()).unwrap(),
+                r#"["x","y"]"#
+            );
+        }
+
+        let unknown = r#"{"a": 1, "b": [0, 0], "c": [], "d": null, "e": {"none": null}, "z": 0}"#;
+        assert!(from_json(InstanceSeed::new(foo_class.clone()), unknown).is_ok());
+        let strict = Policy {
+            deny_unknown_fields: true,
+            ..Policy::default()
+        };
+        assert!(from_json(InstanceSeed::new(foo_class.clone()).policy(strict), unknown).is_err());
+
+        let missing = r#"{"a": 7, "d": false}"#;
+        assert!(from_json(InstanceSeed::new(foo_class.clone()), missing).is_err());
+        let lenient = Policy {
+            deny_missing_fields: false,
+            ..Policy::default()
+        };
+        let partial = from_json(
+            InstanceSeed::new(foo_class.clone()).policy(lenient),
+            missing,
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{:?}", partial),
+            r#"Foo { a: 7, b: [0, 0], c: [], d: Some(false), e: Shape::none }"#
+        );
+
+        for invalid in [
+            r#"{"a": -1}"#,
+            r#"{"b": [1, 2, 3]}"#,
+            r#"{"b": [1]}"#,
+            r#"{"e": {"square": null}}"#,
+            r#"{"e": {"none": null, "circle": 1.0}}"#,
+            r#"{"a": 1, "a": 2}"#,
+        ] {
+            assert!(
+                from_json(
+                    InstanceSeed::new(foo_class.clone()).policy(lenient),
+                    invalid
+                )
+                .is_err(),
+                "{}",
+                invalid
+            );
+        }
+
+        let unit_class: Arc<dyn Class> = Arc::new(Value::<()>::new());
+        let unit = Instance::new(unit_class.clone());
+        assert!(serde_json::to_string(&unit.read().unwrap()).is_err());
+        assert!(from_json(InstanceSeed::new(unit_class.clone()), "null").is_err());
+        let mut codecs = Codecs::default();
+        codecs.register::<()>();
+        let unit = from_json(InstanceSeed::new(unit_class).codecs(&codecs), "null").unwrap();
+        assert_eq!(
+            serde_json::to_string(&unit.read().unwrap().serializable().codecs(&codecs)).unwrap(),
+            "null"
+        );
+    }
+
+    #[test]
+    fn schema_language() {
+        let schema = Schema::parse(
+            "// Definitions may appear in any order
+            object Foo : Base {
+                a: u64;
+                b: i32[4];
+                c: Bar;
+                d: String[]?;
+            }
+            object Base { id: u8; }
+            object Bar { x: f32; y: f32; }
+            enum Shape { none; foo: Foo; }",
+        )
+        .unwrap();
+        assert_eq!(
+            schema.names().collect::<Vec<_>>(),
+            ["Foo", "Base", "Bar", "Shape"]
+        );
+
+        let foo = schema.get("Foo").unwrap();
+        assert!(foo.is_subclass_of(&**schema.get("Base").unwrap()));
+        assert_eq!(foo.attr("id").unwrap().offset, 0);
+        assert_eq!(foo.attr("a").unwrap().offset, 8);
+        assert_eq!(foo.attr("b").item(3).unwrap().offset, 28);
+        assert_eq!(foo.attr("c").attr("y").unwrap().offset, 36);
+        assert!(Arc::ptr_eq(
+            &foo.attr("c").unwrap().class,
+            schema.get("Bar").unwrap()
+        ));
+
+        let instance = Instance::new(schema.get("Shape").unwrap().clone());
+        {
+            let mut write = instance.write().unwrap();
+            WriteReference::of(&mut write).switch("foo").unwrap();
+            write.attr("foo").attr("d").unwrap().set().unwrap();
+        }
+        assert_eq!(
+            format!("{:?}", instance),
+            "Shape::foo(Foo { id: 0, a: 0, b: [0, 0, 0, 0], c: Bar { x: 0.0, y: 0.0 }, d: Some([]) })"
+        );
+        assert_eq!(Schema::parse("").unwrap().names().count(), 0);
+
+        let message = |source: &str| match Schema::parse(source) {
+            Err(Error::SyntaxError(message))
+            | Err(Error::TypeError(message))
+            | Err(Error::AttributeError(message)) => message,
+            Err(error) => panic!("Unexpected error {}", error),
+            Ok(_) => panic!("Expected {:?} to fail", source),
+        };
+        assert_eq!(
+            message("object Foo {\n    a: u64\n}"),
+            "Expected ';' but found '}' at line 3, column 1\n  }\n  ^"
+        );
+        assert_eq!(
+            message("object Foo { a: Missing; }"),
+            "Unknown class Missing at line 1, column 17\n  object Foo { a: Missing; }\n                  ^"
+        );
+        assert!(
+            message("object Foo { a: u64 $ }").starts_with("Unexpected '$' at line 1, column 21")
+        );
+        assert!(message("struct Foo {}")
+            .starts_with("Expected 'object' or 'enum' but found identifier struct"));
+        assert!(message("object Foo { a: u64[x]; }").starts_with("Expected length or ']'"));
+        assert!(message("object Foo { a: u64; }\nobject Foo {}")
+            .starts_with("Class Foo is already defined at line 2"));
+        assert!(message("object u64 {}").starts_with("Class u64 is already defined"));
+        assert!(message("object Foo { a: u64; a: u8; }").contains("already has"));
+        assert!(message("enum Foo {}").starts_with("Enum Foo must have at least one variant"));
+        assert!(message("enum Foo { a; }\nobject Bar : Foo {}")
+            .starts_with("Base class Foo is not an object"));
+        assert!(message("object Foo { a: u64[99999999999999999999]; }").starts_with("Length"));
+        assert!(
+            message("object Foo { a: u64[9999999999999999999]; }").starts_with("Array of length")
+        );
+        let huge = "u8[4611686018427387904]";
+        assert!(
+            message(&format!("object Foo {{ a: {0}; b: {0}; c: {0}; }}", huge))
+                .starts_with("Object Foo is too large at line 1")
+        );
+        assert!(message("object A { b: B; }\nobject B { a: A[2]; }")
+            .starts_with("Class A contains itself through A -> B -> A at line 2, column 15"));
+        assert!(message("object A : A {}").starts_with("Class A contains itself through A -> A"));
+        assert!(message("object A { next: A?; }").starts_with("Class A contains itself"));
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = Registry::new();
+        let u64_class: Arc<dyn Class> = u64::class();
+        assert!(Arc::ptr_eq(registry.get("u64").unwrap(), &u64_class));
+        assert!(Arc::ptr_eq(
+            registry.get_native::<u64>().unwrap(),
+            &u64_class
+        ));
+        assert!(Registry::empty().get("u64").is_none());
+        assert!(registry.get_native::<()>().is_none());
+
+        assert!(matches!(
+            registry.register("u64".into(), Arc::new(Value::<()>::new())),
+            Err(Error::TypeError(_))
+        ));
+        assert!(matches!(
+            registry.register("long".into(), Arc::new(Value::<u64>::new())),
+            Err(Error::TypeError(_))
+        ));
+        assert!(!registry.contains("long"));
+        registry
+            .register("unit".into(), Arc::new(Value::<()>::new()))
+            .unwrap();
+        assert!(registry.get_native::<()>().is_some());
+
+        registry.load("object Base { id: u64; }").unwrap();
+        let schema = registry
+            .load("object Foo : Base { unit: unit; tags: String[]; }")
+            .unwrap();
+        let foo = registry.get("Foo").unwrap().clone();
+        assert!(Arc::ptr_eq(schema.get("Foo").unwrap(), &foo));
+        assert!(foo.is_subclass_of(&**registry.get("Base").unwrap()));
+
+        // Views built against the registered base apply to anything loaded later
+        let id = registry.get("Base").unwrap().attr("id").unwrap();
+        let instance = Instance::new(foo);
+        *instance
+            .write()
+            .unwrap()
+            .through(&id)
+            .unwrap()
+            .cast::<u64>()
+            .unwrap() = 3;
+        assert_eq!(
+            instance
+                .read()
+                .unwrap()
+                .attr("id")
+                .unwrap()
+                .read_as::<u64>()
+                .unwrap(),
+            3
+        );
+
+        assert!(registry
+            .load("object Bar { a: u64; }\nobject Foo {}")
+            .is_err());
+        assert!(registry.load("object Bar { a: Missing; }").is_err());
+        assert!(!registry.contains("Bar"));
+    }
+
+    #[test]
+    fn structural_compatibility() {
+        let schema = "object Point { x: f32; y: f32; }
+            object Particle { position: Point; history: u8[4]; name: String; }";
+        let first = Schema::parse(schema).unwrap();
+        let second = Schema::parse(schema).unwrap();
+        let particle = first.get("Particle").unwrap();
+        let other = second.get("Particle").unwrap();
+        assert!(!particle.is_subclass_of(&**other));
+        assert!(particle.is_identical_to(&**other));
+        assert!(particle.is_compatible_with(&**other));
+
+        let renamed = Schema::parse(
+            "object Other { position: Point; history: u8[4]; name: String; }
+            object Point { x: f32; y: f32; }",
+        )
+        .unwrap();
+        assert!(particle.is_identical_to(&**renamed.get("Other").unwrap()));
+
+        let extended = Schema::parse(
+            "object Point { x: f32; y: f32; }
+            object Particle { position: Point; history: u8[4]; name: String; mass: f64; }",
+        )
+        .unwrap();
+        let extended = extended.get("Particle").unwrap();
+        assert!(!particle.is_identical_to(&**extended));
+        assert!(particle.is_prefix_of(&**extended));
+        assert!(!extended.is_prefix_of(&**particle));
+        assert!(extended.is_compatible_with(&**particle));
+        assert!(!particle.is_compatible_with(&**extended));
+
+        for different in [
+            "object Point { x: f32; y: f32; } object Particle { position: Point; history: u8[3]; name: String; }",
+            "object Point { x: f32; y: f32; } object Particle { position: Point; history: i8[4]; name: String; }",
+            "object Point { y: f32; x: f32; } object Particle { position: Point; history: u8[4]; name: String; }",
+            "object Point { x: f32; y: f32; } object Particle { position: Point; name: String; history: u8[4]; }",
+        ] {
+            let different = Schema::parse(different).unwrap();
+            let different = different.get("Particle").unwrap();
+            assert!(!particle.is_identical_to(&**different));
+            assert!(!particle.is_prefix_of(&**different));
+        }
+        let short: Arc<dyn Class> = <[u8; 2]>::class();
+        let long: Arc<dyn Class> = <[u8; 4]>::class();
+        assert!(short.is_prefix_of(&*long));
+        assert!(!long.is_prefix_of(&*short));
+
+        // Views and typed views built against one schema apply to instances of another
+        let y = Path::parse("position.y")
+            .unwrap()
+            .view(other.clone())
+            .unwrap();
+        let typed = y.clone().typed::<f32>().unwrap();
+        let instance = Instance::new(extended.clone());
+        {
+            let mut write = instance.write().unwrap();
+            *write.through(&y).unwrap().cast::<f32>().unwrap() = 1.5;
+            *typed.get_mut(&mut write) += 1.0;
+        }
+        let read = instance.read().unwrap();
+        assert_eq!(*typed.get(&read), 2.5);
+        let upcast = ReadReference::of(&read).upcast(other).unwrap();
+        assert!(upcast.attr("mass").is_err());
+        let unrelated = Instance::new(first.get("Point").unwrap().clone());
+        assert!(unrelated.read().unwrap().through(&y).is_err());
+    }
+
+    #[test]
+    fn migration() {
+        let mut registry = Registry::new();
+        registry
+            .register("Tracked".into(), Arc::new(Value::<Tracked>::new()))
+            .unwrap();
+        let old = Schema::parse_with(
+            "object Point { x: f32; y: f32; }
+             object Thing { name: String; count: u32; position: Point; legacy: Tracked;
+                            kept: Tracked; label: String; }",
+            &registry,
+        )
+        .unwrap();
+        let new = Schema::parse_with(
+            "object Point { x: f32; y: f32; z: f32; }
+             object Thing { title: String; count: u64; position: Point; kept: Tracked;
+                            label_length: usize; extra: bool; }",
+            &registry,
+        )
+        .unwrap();
+        let from = old
+            .get("Thing")
+            .unwrap()
+            .clone()
+            .downcast_arc::<Object>()
+            .unwrap();
+        let to = new
+            .get("Thing")
+            .unwrap()
+            .clone()
+            .downcast_arc::<Object>()
+            .unwrap();
+
+        let mut instance = Instance::new(from.clone());
+        {
+            let mut write = instance.write().unwrap();
+            *write.attr("name").unwrap().cast::<String>().unwrap() = "hello".into();
+            write.attr("count").unwrap().write_from(7u32).unwrap();
+            write
+                .attr("position")
+                .attr("x")
+                .unwrap()
+                .write_from(1.5f32)
+                .unwrap();
+            *write.attr("label").unwrap().cast::<String>().unwrap() = "four".into();
+        }
+        assert_eq!(constructed(), 2);
+
+        let mut migration = Migration::new(from.clone(), to.clone());
+        migration.rename("name".into(), "title".into()).unwrap();
+        migration.convert("label_length".into(), |old, new| {
+            let label = old.clone().attr("label")?;
+            new.write_from(label.cast::<String>()?.len())
+        });
+        assert!(migration.rename("position.x".into(), "x".into()).is_err());
+
+        let report = migration.apply(&mut instance).unwrap();
+        assert_eq!(
+            report,
+            Report {
+                moved: vec!["position.x".into(), "position.y".into(), "kept".into()],
+                renamed: vec![("name".into(), "title".into())],
+                converted: vec!["count".into(), "label_length".into()],
+                added: vec!["position.z".into(), "extra".into()],
+                removed: vec!["legacy".into(), "label".into()],
+            }
+        );
+        // The default of the new kept member is replaced, the removed member is destroyed
+        assert_eq!(destroyed(), vec![2, 0]);
+
+        {
+            let read = instance.read().unwrap();
+            assert!(Arc::ptr_eq(read.class(), &(to.clone() as Arc<dyn Class>)));
+            assert_eq!(
+                read.attr("title").unwrap().cast::<String>().unwrap(),
+                "hello"
+            );
+            assert_eq!(read.attr("count").unwrap().read_as::<u64>().unwrap(), 7);
+            assert_eq!(
+                read.attr("position")
+                    .attr("x")
+                    .unwrap()
+                    .read_as::<f32>()
+                    .unwrap(),
+                1.5
+            );
+            assert_eq!(read.attr("kept").unwrap().cast::<Tracked>().unwrap().0, 1);
+            assert_eq!(
+                read.attr("label_length")
+                    .unwrap()
+                    .read_as::<usize>()
+                    .unwrap(),
+                4
+            );
+        }
+        drop(instance);
+        assert_eq!(destroyed(), vec![1]);
+
+        // Failed migrations leave the instance as it was
+        let retyped = Schema::parse_with(
+            "object Point { x: f32; y: f32; }
+             object Thing { name: u32; }",
+            &registry,
+        )
+        .unwrap();
+        let retyped = retyped
+            .get("Thing")
+            .unwrap()
+            .clone()
+            .downcast_arc::<Object>()
+            .unwrap();
+        let mut instance = Instance::new(from.clone());
+        let migration = Migration::new(from.clone(), retyped.clone());
+        assert!(matches!(
+            migration.apply(&mut instance),
+            Err(Error::TypeError(_))
+        ));
+        assert!(instance.read().unwrap().class().is_identical_to(&*from));
+        assert!(matches!(
+            Migration::new(retyped, to).apply(&mut instance),
+            Err(Error::TypeError(_))
+        ));
+        drop(instance);
+        destroyed();
+    }
+
+    #[test]
+    fn custom_defaults() {
+        struct Handle(u32);
+
+        let mut builder = Builder::new("Player".into());
+        builder.add_with_default("health".into(), 100u32).unwrap();
+        builder
+            .add_with_default("name".into(), String::from("anonymous"))
+            .unwrap();
+        builder
+            .add("handle".into(), Arc::new(Value::from_fn(|| Handle(7))))
+            .unwrap();
+        assert!(builder.add_with_default("health".into(), 1u32).is_err());
+        let player: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let instance = Instance::new(player);
+        {
+            let read = instance.read().unwrap();
+            assert_eq!(read.attr("health").unwrap().read_as::<u32>().unwrap(), 100);
+            assert_eq!(
+                read.attr("name").unwrap().cast::<String>().unwrap(),
+                "anonymous"
+            );
+            assert_eq!(read.attr("handle").unwrap().cast::<Handle>().unwrap().0, 7);
+        }
+
+        // Defaults make a new class that keeps the operations of the one it is made from
+        let health = u32::class().with_default(100);
+        assert!(health.supports(Operation::Hash));
+        assert!(!Value::from_fn(|| 0u32)
+            .with_default(3)
+            .supports(Operation::Clone));
+        let instance = Instance::new(Arc::new(health)).try_clone().unwrap();
+        assert_eq!(
+            ReadReference::of(&instance.read().unwrap())
+                .read_as::<u32>()
+                .unwrap(),
+            100
+        );
+    }
+
+    #[test]
+    fn columnar() {
+        let mut builder = Builder::new("Point".into());
+        builder.add("x".into(), f32::class()).unwrap();
+        builder.add("y".into(), f32::class()).unwrap();
+        builder.add("id".into(), u64::class()).unwrap();
+        let point = Arc::new(Object::new(builder));
+        let columnar = Columnar::new(point, 3).unwrap();
+        assert_eq!(columnar.column("y").unwrap().offset, 12);
+        assert_eq!(columnar.column("id").unwrap().offset, 24);
+        assert_eq!(columnar.size(), 48);
+        assert!(columnar.supports(Operation::Clone));
+        let class: Arc<dyn Class> = Arc::new(columnar);
+
+        let instance = Instance::new(class.clone());
+        {
+            let mut write = instance.write().unwrap();
+            write.item(1).attr("x").unwrap().write_from(2.0f32).unwrap();
+            let mut reference = WriteReference::of(&mut write);
+            reference.column_mut::<f32>("y").unwrap()[2] = 5.0;
+            for (i, id) in reference
+                .column_mut::<u64>("id")
+                .unwrap()
+                .iter_mut()
+                .enumerate()
+            {
+                *id = i as u64 * 10;
+            }
+            assert!(matches!(
+                reference.column_mut::<u32>("x"),
+                Err(Error::ValueError(_))
+            ));
+            for (name, mut member) in reference.item(0).unwrap().members().unwrap() {
+                if name == "y" {
+                    member.write_from(1.0f32).unwrap();
+                }
+            }
+        }
+
+        let read = instance.read().unwrap();
+        let reference = ReadReference::of(&read);
+        assert_eq!(reference.column::<f32>("x").unwrap(), &[0.0, 2.0, 0.0]);
+        assert_eq!(reference.column::<f32>("y").unwrap(), &[1.0, 0.0, 5.0]);
+        let row = read.item(1).unwrap();
+        let members: Vec<(&str, f64)> = row
+            .members()
+            .unwrap()
+            .map(|(name, member)| (name, member.read_as::<f64>().unwrap()))
+            .collect();
+        assert_eq!(members, [("x", 2.0), ("y", 0.0), ("id", 10.0)]);
+        assert_eq!(
+            read.item(2).attr("y").unwrap().read_as::<f32>().unwrap(),
+            5.0
+        );
+        assert_eq!(read.attr("id").unwrap().len().unwrap(), 3);
+
+        // Rows are kept, so every lens to an item has the same class
+        assert_eq!(
+            class.item(1).unwrap().class.id(),
+            class.item(1).unwrap().class.id()
+        );
+        assert_ne!(
+            class.item(1).unwrap().class.id(),
+            class.item(2).unwrap().class.id()
+        );
+
+        // Views built through rows resolve to fixed offsets into the columns
+        let id = class.item(2).attr("id").unwrap();
+        assert_eq!(read.through(&id).unwrap().read_as::<u64>().unwrap(), 20);
+        assert_eq!(
+            format!("{:?}", read.item(1).unwrap().pretty()),
+            "Point { x: 2.0, y: 0.0, id: 10 }"
+        );
+        assert!(format!("{}", read.pretty()).starts_with("[Point { x: 0.0, y: 1.0, id: 0 }, "));
+
+        assert!(matches!(
+            reference.column::<f32>("z"),
+            Err(Error::AttributeError(_))
+        ));
+        assert!(matches!(read.item(3), Err(Error::IndexError(_))));
+        assert!(read.item(0).item(0).is_err());
+        drop(read);
+        assert!(instance.try_clone().unwrap().try_eq(&instance).unwrap());
+    }
+
+    #[test]
+    fn array_slices() {
+        let mut builder = Builder::new("Samples".into());
+        builder
+            .add(
+                "values".into(),
+                Arc::new(Array::new(f32::class(), 4).unwrap()),
+            )
+            .unwrap();
+        builder.add("count".into(), u8::class()).unwrap();
+        let samples: Arc<dyn Class> = Arc::new(Object::new(builder));
+        let instance = Instance::new(samples);
+
+        {
+            let mut write = instance.write().unwrap();
+            let mut values = write.attr("values").unwrap();
+            values
+                .as_mut_slice::<f32>()
+                .unwrap()
+                .copy_from_slice(&[1.0, 2.0, 3.0, 4.0]);
+            assert!(matches!(
+                values.as_mut_slice::<f64>(),
+                Err(Error::ValueError(_))
+            ));
+            assert!(matches!(
+                write.attr("count").unwrap().as_mut_slice::<u8>(),
+                Err(Error::TypeError(_))
+            ));
+        }
+
+        let read = instance.read().unwrap();
+        let values = read.attr("values").unwrap();
+        assert_eq!(values.as_slice::<f32>().unwrap().iter().sum::<f32>(), 10.0);
+        assert_eq!(values.item(3).unwrap().read_as::<f32>().unwrap(), 4.0);
+        assert!(ReadReference::of(&read).as_slice::<f32>().is_err());
+
+        // A u16 stored in four bytes, so elements are padded apart
+        #[derive(Debug)]
+        struct Padded(Value<u16>);
+
+        impl Unique for Padded {
+            fn id(&self) -> &Id {
+                self.0.id()
+            }
+        }
+
+        unsafe impl Accessor<Lens> for Padded {
+            fn attr(&self, name: &str) -> crate::error::Result<Lens> {
+                self.0.attr(name)
+            }
+
+            fn item(&self, index: usize) -> crate::error::Result<Lens> {
+                self.0.item(index)
+            }
+        }
+
+        unsafe impl Metaclass for Padded {
+            unsafe fn construct(&self, data: *mut u8) {
+                self.0.construct(data);
+            }
+
+            unsafe fn destroy(&self, data: *mut u8) {
+                self.0.destroy(data);
+            }
+        }
+
+        unsafe impl Class for Padded {
+            fn size(&self) -> usize {
+                4
+            }
+
+            fn align(&self) -> usize {
+                4
+            }
+
+            fn layout(&self) -> Layout {
+                Layout::from_size_align(4, 4).unwrap()
+            }
+
+            fn value(&self) -> Option<TypeId> {
+                self.0.value()
+            }
+        }
+
+        let padded = Arc::new(Padded(Value::new()));
+        let instance = Instance::new(Arc::new(Array::new(padded, 3).unwrap()));
+        let mut write = instance.write().unwrap();
+        assert!(matches!(
+            WriteReference::of(&mut write).as_mut_slice::<u16>(),
+            Err(Error::TypeError(_))
+        ));
+        drop(write);
+        let read = instance.read().unwrap();
+        assert!(matches!(
+            ReadReference::of(&read).as_slice::<u16>(),
+            Err(Error::TypeError(_))
+        ));
+        assert!(read.item(2).unwrap().read_as::<u16>().is_ok());
+    }
+
+    #[test]
+    fn iterators() {
+        let mut builder = Builder::new("Foo".into());
+        builder.add("a".into(), u32::class()).unwrap();
+        builder
+            .add("b".into(), Arc::new(Array::new(u8::class(), 3).unwrap()))
+            .unwrap();
+        builder
+            .add("c".into(), Arc::new(List::new(u16::class())))
+            .unwrap();
+        let foo: Arc<dyn Class> = Arc::new(Object::new(builder));
+        let instance = Instance::new(foo);
+
+        {
+            let mut write = instance.write().unwrap();
+            let mut root = WriteReference::of(&mut write);
+            for (name, mut member) in root.members().unwrap() {
+                if name == "a" {
+                    member.write_from(7u32).unwrap();
+                }
+            }
+            let mut items = root.reborrow().attr("b").unwrap();
+            for (i, mut item) in items.items().unwrap().enumerate() {
+                item.write_from(i as u8 + 1).unwrap();
+            }
+            let mut list = root.attr("c").unwrap();
+            list.push().unwrap();
+            list.push().unwrap();
+            // Elements are disjoint, so all of them may be held at once
+            let mut items: Vec<_> = list.items().unwrap().collect();
+            items[1].write_from(20u16).unwrap();
+            items[0].write_from(10u16).unwrap();
+        }
+
+        let read = instance.read().unwrap();
+        let root = ReadReference::of(&read);
+        let names: Vec<&str> = root.members().unwrap().map(|(name, _)| name).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        let (_, a) = root.members().unwrap().next().unwrap();
+        assert_eq!(a.read_as::<u32>().unwrap(), 7);
+        let b: Vec<u8> = read
+            .attr("b")
+            .unwrap()
+            .items()
+            .unwrap()
+            .map(|item| item.read_as().unwrap())
+            .collect();
+        assert_eq!(b, [1, 2, 3]);
+        let c: Vec<u16> = read
+            .attr("c")
+            .unwrap()
+            .items()
+            .unwrap()
+            .map(|item| *item.cast::<u16>().unwrap())
+            .collect();
+        assert_eq!(c, [10, 20]);
+
+        assert!(matches!(root.items(), Err(Error::TypeError(_))));
+        assert!(matches!(
+            read.attr("a").unwrap().members(),
+            Err(Error::TypeError(_))
+        ));
+    }
+
+    /// Counts the allocations it hands out on behalf of the global allocator.
+    #[derive(Default)]
+    struct Counting {
+        live: std::sync::atomic::AtomicUsize,
+    }
+
+    unsafe impl Allocator for Counting {
+        unsafe fn allocate(&self, layout: std::alloc::Layout) -> *mut u8 {
+            self.live.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, data: *mut u8, layout: std::alloc::Layout) {
+            self.live.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+            Global.deallocate(data, layout)
+        }
+    }
+
+    #[test]
+    fn allocators() {
+        let mut builder = Builder::new("Foo".into());
+        builder.add("a".into(), u64::class()).unwrap();
+        builder.add("b".into(), String::class()).unwrap();
+        let foo: Arc<dyn Class> = Arc::new(Object::new(builder));
+
+        let counting = Arc::new(Counting::default());
+        let live = || counting.live.load(std::sync::atomic::Ordering::Relaxed);
+        let instance = Instance::new_in(foo.clone(), counting.clone());
+        *instance
+            .write()
+            .unwrap()
+            .attr("b")
+            .unwrap()
+            .cast::<String>()
+            .unwrap() = "heap allocated".into();
+        assert_eq!(live(), 1);
+        // Clones come from the same allocator
+        let clone = instance.try_clone().unwrap();
+        assert_eq!(live(), 2);
+        assert!(clone.try_eq(&instance).unwrap());
+        drop(instance);
+        drop(clone);
+        assert_eq!(live(), 0);
+
+        // Adopted buffers are destroyed but left to their owner
+        let tracked: Arc<dyn Class> = Arc::new(Value::<Tracked>::new());
+        let mut builder = Builder::new("Bar".into());
+        builder.add("a".into(), u64::class()).unwrap();
+        builder.add("tracked".into(), tracked).unwrap();
+        let bar = Arc::new(Object::new(builder));
+        let mut buffer = [0u64; 2];
+        let instance = unsafe { Instance::in_buffer(bar.clone(), buffer.as_mut_ptr().cast()) };
+        instance
+            .write()
+            .unwrap()
+            .attr("a")
+            .unwrap()
+            .write_from(3u64)
+            .unwrap();
+        let index = constructed() - 1;
+        drop(instance);
+        assert_eq!(destroyed(), vec![index]);
+        assert_eq!(buffer[0], 3);
+
+        // Migrating moves the instance out of its buffer, which is left to its owner
+        let mut builder = Builder::new("Bar".into());
+        builder.add("a".into(), u64::class()).unwrap();
+        let migration = Migration::new(bar.clone(), Arc::new(Object::new(builder)));
+        let mut instance = unsafe { Instance::in_buffer(bar.clone(), buffer.as_mut_ptr().cast()) };
+        let index = constructed() - 1;
+        migration.apply(&mut instance).unwrap();
+        assert_eq!(destroyed(), vec![index]);
+        assert_ne!(instance.read().unwrap().data(), buffer.as_mut_ptr().cast());
+        drop(instance);
+        assert!(destroyed().is_empty());
+
+        let misaligned = unsafe { buffer.as_mut_ptr().cast::<u8>().add(1) };
+        let misaligned = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+            Instance::in_buffer(bar, misaligned)
+        }));
+        assert!(misaligned.is_err());
+    }
 }
@@ -0,0 +1,276 @@
+use crate::class::object::Object;
+use crate::class::pointer::Pointer;
+use crate::class::Class;
+use crate::error::{Error, Result};
+use crate::instance::read::ReadReference;
+use crate::instance::write::{self, WriteReference};
+use crate::instance::Instance;
+use crate::numeric;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// Fills a member of the new instance, given the whole old instance.
+pub type Converter =
+    Box<dyn Fn(&ReadReference<'_>, &mut WriteReference<'_>) -> Result<()> + Send + Sync>;
+
+/// Moves instances of an object class into a newer version of it.
+///
+/// Members of the new class are filled from the old member of the same name, or the name it was
+/// renamed from. Members whose class is unchanged are moved as is, nested objects are migrated
+/// member by member and numeric members are converted between numeric types. Any other change
+/// needs a converter. Members only in the new class keep their defaults and members only in the
+/// old class are destroyed. Nested members are named by dotted paths such as `position.x`.
+pub struct Migration {
+    from: Arc<Object>,
+    to: Arc<Object>,
+    renames: HashMap<String, String>,
+    converters: HashMap<String, Converter>,
+}
+
+/// What a migration did to each member, by path.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub moved: Vec<String>,
+    /// Old and new paths of members that were moved under a new name.
+    pub renamed: Vec<(String, String)>,
+    pub converted: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+struct Move {
+    class: Arc<dyn Class>,
+    from: usize,
+    to: usize,
+}
+
+struct Context<'a, 'g> {
+    source: &'a ReadReference<'g>,
+    from: *mut u8,
+    /// The new instance's data, write-locked for as long as the context lives.
+    to: *mut u8,
+}
+
+#[derive(Default)]
+struct Plan {
+    moves: Vec<Move>,
+    /// Old paths of members moved as a whole.
+    moved: HashSet<String>,
+    /// Old paths of objects migrated member by member.
+    nested: HashSet<String>,
+    report: Report,
+}
+
+impl Migration {
+    pub fn new(from: Arc<Object>, to: Arc<Object>) -> Self {
+        Migration {
+            from,
+            to,
+            renames: HashMap::new(),
+            converters: HashMap::new(),
+        }
+    }
+
+    /// Fill the new member at path `to` from the old member at path `from`. Both must be in the
+    /// same object.
+    pub fn rename(&mut self, from: String, to: String) -> Result<()> {
+        if parent(&from) != parent(&to) {
+            return Err(Error::AttributeError(format!(
+                "Cannot rename {} to {} in a different object!",
+                from, to
+            )));
+        }
+        self.renames.insert(to, from);
+        Ok(())
+    }
+
+    /// Fill the new member at path with a converter instead of the old member.
+    pub fn convert<F>(&mut self, path: String, converter: F)
+    where
+        F: Fn(&ReadReference<'_>, &mut WriteReference<'_>) -> Result<()> + Send + Sync + 'static,
+    {
+        self.converters.insert(path, Box::new(converter));
+    }
+
+    /// Replace an instance of the old class with one of the new class. The instance is left
+    /// untouched if any member cannot be migrated.
+    ///
+    /// The new data comes from the instance's allocator. An instance in an adopted buffer is moved
+    /// to the global allocator instead, since the new class may not fit the buffer, and the buffer
+    /// is left to its owner as if the instance had been dropped.
+    pub fn apply(&self, instance: &mut Instance) -> Result<Report> {
+        let target = Instance::new_in(self.to.clone(), instance.allocator());
+        let plan = {
+            let source = instance.read().unwrap_or_else(|error| error.into_inner());
+            if !source.class().is_identical_to(&*self.from) {
+                return Err(Error::TypeError(format!(
+                    "Migration from {:?} cannot be applied to instance of type {:?}!",
+                    self.from,
+                    source.class()
+                )));
+            }
+            let write = target.write().unwrap();
+            let context = Context {
+                source: &ReadReference::of(&source),
+                from: source.data(),
+                to: write.data(),
+            };
+            let mut plan = Plan::default();
+            self.plan(&self.from, &self.to, ("", ""), (0, 0), &context, &mut plan)?;
+
+            // Invariant: every move is between identical classes at offsets of constructed members,
+            // and the default in the target is destroyed before it is overwritten
+            let (from, to) = (context.from, context.to);
+            for Move {
+                class,
+                from: old,
+                to: new,
+            } in &plan.moves
+            {
+                unsafe {
+                    class.destroy(to.add(*new));
+                    std::ptr::copy_nonoverlapping(from.add(*old), to.add(*new), class.size());
+                }
+            }
+            plan
+        };
+
+        // Moved members now belong to the target, so only the rest of the old data is destroyed
+        let (class, data, storage) = std::mem::replace(instance, target).into_raw();
+        unsafe {
+            destroy_except(&self.from, data, "", &plan);
+            storage.release(data, class.layout());
+        }
+        Ok(plan.report)
+    }
+
+    fn plan(
+        &self,
+        from: &Object,
+        to: &Object,
+        (old_prefix, prefix): (&str, &str),
+        (old, new): (usize, usize),
+        context: &Context<'_, '_>,
+        plan: &mut Plan,
+    ) -> Result<()> {
+        let mut used = HashSet::new();
+        for member in to.members() {
+            let path = format!("{}{}", prefix, member.name);
+            let name = match self.renames.get(&path) {
+                Some(renamed) => last(renamed),
+                None => &member.name,
+            };
+            let original = from.member(name);
+            if original.is_some() {
+                used.insert(name.to_string());
+            }
+
+            if let Some(converter) = self.converters.get(&path) {
+                // Invariant: the new member at offset is constructed and nothing else references it
+                // while the converter runs
+                let target = Pointer {
+                    class: member.class.clone(),
+                    data: unsafe { context.to.add(new + member.offset) },
+                };
+                converter(context.source, &mut unsafe { write::lend(target) })?;
+                plan.report.converted.push(path);
+                continue;
+            }
+            let Some(original) = original else {
+                plan.report.added.push(path);
+                continue;
+            };
+            let old_path = format!("{}{}", old_prefix, name);
+            if plan.moved.contains(&old_path) || plan.nested.contains(&old_path) {
+                return Err(Error::AttributeError(format!(
+                    "Member {} cannot be migrated into more than one member!",
+                    old_path
+                )));
+            }
+            let (old, new) = (old + original.offset, new + member.offset);
+
+            if original.class.is_identical_to(&*member.class) {
+                plan.moves.push(Move {
+                    class: member.class.clone(),
+                    from: old,
+                    to: new,
+                });
+                plan.moved.insert(old_path.clone());
+                if old_path == path {
+                    plan.report.moved.push(path);
+                } else {
+                    plan.report.renamed.push((old_path, path));
+                }
+            } else if let (Some(a), Some(b)) = (
+                original.class.downcast_ref::<Object>(),
+                member.class.downcast_ref::<Object>(),
+            ) {
+                plan.nested.insert(old_path.clone());
+                let prefixes = (&*format!("{}.", old_path), &*format!("{}.", path));
+                self.plan(a, b, prefixes, (old, new), context, plan)?;
+            } else {
+                // Invariant: both offsets are of constructed members of the locked instances
+                let data = unsafe { (context.from.add(old), context.to.add(new)) };
+                let number = unsafe { numeric::load(&*original.class, data.0) }.map_err(|_| {
+                    Error::TypeError(format!(
+                        "Member {} cannot be migrated from {:?} to {:?} without a converter!",
+                        path, original.class, member.class
+                    ))
+                })?;
+                unsafe { numeric::store(&*member.class, data.1, number) }.map_err(|error| {
+                    error.map(|message| format!("Cannot migrate member {}: {}", path, message))
+                })?;
+                plan.report.converted.push(path);
+            }
+        }
+
+        for member in from.members() {
+            if !used.contains(&member.name) {
+                plan.report
+                    .removed
+                    .push(format!("{}{}", old_prefix, member.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Migration {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("Migration")
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .field("renames", &self.renames)
+            .field("converters", &self.converters.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// # Safety
+///
+/// `data` must have been constructed by object, except for the members moved out by plan.
+unsafe fn destroy_except(object: &Object, data: *mut u8, prefix: &str, plan: &Plan) {
+    for member in object.members() {
+        let path = format!("{}{}", prefix, member.name);
+        let data = data.add(member.offset);
+        if plan.moved.contains(&path) {
+            continue;
+        }
+        match member.class.downcast_ref::<Object>() {
+            Some(nested) if plan.nested.contains(&path) => {
+                destroy_except(nested, data, &format!("{}.", path), plan);
+            }
+            _ => member.class.destroy(data),
+        }
+    }
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('.').map_or("", |(parent, _)| parent)
+}
+
+fn last(path: &str) -> &str {
+    path.rsplit_once('.').map_or(path, |(_, name)| name)
+}
@@ -0,0 +1,64 @@
+use crate::class::array::Array;
+use crate::class::value::Value;
+use crate::class::Class;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+#[cfg(feature = "derive")]
+pub use objective_derive::Objective;
+
+/// A Rust type whose memory layout is described exactly by a class, so that instances of the class
+/// may be reinterpreted as the type and vice versa.
+///
+/// Derive this for `#[repr(C)]` structs with `#[derive(Objective)]`. The derive rejects structs
+/// that implement `Drop` themselves, since instances destroy their members individually.
+///
+/// # Safety
+///
+/// `class()` must describe a layout identical to `Layout::new::<Self>()`, constructing and
+/// destroying values the same way Rust does, and must return the same class on every call.
+pub unsafe trait Objective: Sized + 'static {
+    type Class: Class;
+
+    fn class() -> Arc<Self::Class>;
+}
+
+macro_rules! values {
+    ($($type:ty),* => $class:expr) => {
+        $(
+            unsafe impl Objective for $type {
+                type Class = Value<$type>;
+
+                fn class() -> Arc<Value<$type>> {
+                    static CLASS: OnceLock<Arc<Value<$type>>> = OnceLock::new();
+                    CLASS.get_or_init(|| Arc::new($class)).clone()
+                }
+            }
+        )*
+    };
+}
+
+values!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, String
+    => Value::new().with_clone().with_eq().with_hash()
+);
+values!(f32, f64 => Value::new().with_clone().with_eq());
+
+unsafe impl<T: Objective, const N: usize> Objective for [T; N] {
+    type Class = Array;
+
+    fn class() -> Arc<Array> {
+        // Statics are shared between every monomorphization, so key the cache by type
+        static CLASSES: OnceLock<Mutex<HashMap<TypeId, Arc<Array>>>> = OnceLock::new();
+        let element = T::class();
+        CLASSES
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .entry(TypeId::of::<Self>())
+            // [T; N] is a Rust type, so its layout always fits in an allocation
+            .or_insert_with(|| Arc::new(Array::new(element, N).unwrap()))
+            .clone()
+    }
+}
@@ -0,0 +1,135 @@
+use crate::class::Class;
+use crate::error::{Error, Result};
+use std::any::{type_name, TypeId};
+
+/// An intermediate wide enough to hold any primitive numeric value without loss.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Number {
+    Int(i128),
+    /// Only for values above `i128::MAX`, which only `u128` can hold.
+    UInt(u128),
+    Float(f64),
+}
+
+/// Primitive numeric types that can be converted between each other when reading and writing.
+pub trait Numeric: Copy + 'static {
+    fn to_number(self) -> Number;
+
+    /// Returns None if the number cannot be represented by this type without overflow or, for
+    /// integers, without losing a fractional part.
+    fn from_number(number: Number) -> Option<Self>;
+}
+
+macro_rules! integers {
+    ($($type:ty),*) => {
+        $(
+            impl Numeric for $type {
+                fn to_number(self) -> Number {
+                    match i128::try_from(self) {
+                        Ok(value) => Number::Int(value),
+                        Err(_) => Number::UInt(self as u128),
+                    }
+                }
+
+                fn from_number(number: Number) -> Option<Self> {
+                    match number {
+                        Number::Int(value) => <$type>::try_from(value).ok(),
+                        Number::UInt(value) => <$type>::try_from(value).ok(),
+                        // Also rejects NaN and infinities, whose fractional part is NaN
+                        Number::Float(value) if value.fract() != 0.0 => None,
+                        // Casts saturate, so check the range of the widest types first
+                        Number::Float(value) if value < 0.0 => (value >= i128::MIN as f64)
+                            .then(|| value as i128)
+                            .and_then(|value| <$type>::try_from(value).ok()),
+                        Number::Float(value) => (value < u128::MAX as f64)
+                            .then(|| value as u128)
+                            .and_then(|value| <$type>::try_from(value).ok()),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! floats {
+    ($($type:ty),*) => {
+        $(
+            impl Numeric for $type {
+                fn to_number(self) -> Number {
+                    Number::Float(self as f64)
+                }
+
+                fn from_number(number: Number) -> Option<Self> {
+                    match number {
+                        // Only integers that survive the round trip are represented exactly, and
+                        // casting back saturates, so the upper bound itself must be excluded
+                        Number::Int(value) => {
+                            let converted = value as $type;
+                            (converted < i128::MAX as $type && converted as i128 == value)
+                                .then_some(converted)
+                        }
+                        Number::UInt(value) => {
+                            let converted = value as $type;
+                            (converted < u128::MAX as $type && converted as u128 == value)
+                                .then_some(converted)
+                        }
+                        Number::Float(value) => {
+                            let converted = value as $type;
+                            (converted.is_finite() || !value.is_finite()).then_some(converted)
+                        }
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+floats!(f32, f64);
+
+macro_rules! numerics {
+    ($($type:ty),*) => {
+        /// # Safety
+        ///
+        /// `data` must have been constructed by class.
+        pub(crate) unsafe fn load(class: &dyn Class, data: *const u8) -> Result<Number> {
+            let type_id = class.value();
+            $(
+                if type_id == Some(TypeId::of::<$type>()) {
+                    return Ok((*data.cast::<$type>()).to_number());
+                }
+            )*
+            Err(not_numeric(class))
+        }
+
+        /// # Safety
+        ///
+        /// `data` must have been constructed by class.
+        pub(crate) unsafe fn store(class: &dyn Class, data: *mut u8, number: Number) -> Result<()> {
+            let type_id = class.value();
+            $(
+                if type_id == Some(TypeId::of::<$type>()) {
+                    *data.cast::<$type>() = convert(number)?;
+                    return Ok(());
+                }
+            )*
+            Err(not_numeric(class))
+        }
+    };
+}
+
+numerics!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+fn not_numeric(class: &dyn Class) -> Error {
+    Error::TypeError(format!("Class {:?} is not numeric!", class))
+}
+
+pub(crate) fn convert<T: Numeric>(number: Number) -> Result<T> {
+    T::from_number(number).ok_or_else(|| {
+        Error::ValueError(format!(
+            "{:?} cannot be represented as {}!",
+            number,
+            type_name::<T>()
+        ))
+    })
+}
@@ -0,0 +1,175 @@
+use crate::accessor::IntoAccessor;
+use crate::class::view::View;
+use crate::class::Class;
+use crate::error::{Error, Result};
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+use std::sync::Arc;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Segment {
+    Attr(String),
+    Item(usize),
+}
+
+/// A parsed chain of attribute and item accesses such as `a.b[2].c`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Path {
+    pub segments: Vec<Segment>,
+}
+
+impl Path {
+    pub fn parse(source: &str) -> Result<Path> {
+        Parser::new(source).parse()
+    }
+
+    /// Walk the path starting from target, reporting how far it got on failure.
+    pub fn apply<T: IntoAccessor<T>>(&self, target: T) -> Result<T> {
+        let mut target = target;
+        for (i, segment) in self.segments.iter().enumerate() {
+            let result = match segment {
+                Segment::Attr(name) => target.attr(name),
+                Segment::Item(index) => target.item(*index),
+            };
+            target = result.map_err(|error| {
+                let resolved = Path {
+                    segments: self.segments[..i].to_vec(),
+                };
+                error.map(|message| {
+                    format!("{} (resolved \"{}\" of \"{}\")", message, resolved, self)
+                })
+            })?;
+        }
+        Ok(target)
+    }
+
+    pub fn view(&self, class: Arc<dyn Class>) -> Result<View> {
+        self.apply(View::of(class))
+    }
+}
+
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Path> {
+        Path::parse(source)
+    }
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Attr(name) if i == 0 => write!(formatter, "{}", name)?,
+                Segment::Attr(name) => write!(formatter, ".{}", name)?,
+                Segment::Item(index) => write!(formatter, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    characters: Peekable<CharIndices<'s>>,
+}
+
+impl<'s> Parser<'s> {
+    fn new(source: &'s str) -> Self {
+        Parser {
+            source,
+            characters: source.char_indices().peekable(),
+        }
+    }
+
+    fn position(&mut self) -> usize {
+        self.characters
+            .peek()
+            .map(|(position, _)| *position)
+            .unwrap_or(self.source.len())
+    }
+
+    fn error(&mut self, expected: &str) -> Error {
+        let position = self.position();
+        let found = match self.source[position..].chars().next() {
+            Some(character) => format!("{:?}", character),
+            None => "end of path".into(),
+        };
+        let column = self.source[..position].chars().count();
+        Error::SyntaxError(format!(
+            "Expected {} but found {} at column {}\n  {}\n  {}^",
+            expected,
+            found,
+            column + 1,
+            self.source,
+            " ".repeat(column)
+        ))
+    }
+
+    fn parse(mut self) -> Result<Path> {
+        let mut segments = Vec::new();
+        match self.characters.peek() {
+            None => return Ok(Path { segments }),
+            Some((_, '[')) => {}
+            Some(_) => segments.push(Segment::Attr(self.identifier()?)),
+        }
+
+        while let Some((_, character)) = self.characters.peek() {
+            match character {
+                '.' => {
+                    self.characters.next();
+                    segments.push(Segment::Attr(self.identifier()?));
+                }
+                '[' => {
+                    self.characters.next();
+                    segments.push(Segment::Item(self.index()?));
+                    match self.characters.peek() {
+                        Some((_, ']')) => self.characters.next(),
+                        _ => return Err(self.error("']'")),
+                    };
+                }
+                _ => return Err(self.error("'.' or '['")),
+            }
+        }
+
+        Ok(Path { segments })
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        let start = self.position();
+        match self.characters.peek() {
+            Some((_, character)) if character.is_alphabetic() || *character == '_' => {}
+            _ => return Err(self.error("identifier")),
+        }
+        while let Some((_, character)) = self.characters.peek() {
+            if character.is_alphanumeric() || *character == '_' {
+                self.characters.next();
+            } else {
+                break;
+            }
+        }
+        Ok(self.source[start..self.position()].into())
+    }
+
+    fn index(&mut self) -> Result<usize> {
+        let start = self.position();
+        while let Some((_, character)) = self.characters.peek() {
+            if character.is_ascii_digit() {
+                self.characters.next();
+            } else {
+                break;
+            }
+        }
+        let end = self.position();
+        if start == end {
+            return Err(self.error("index"));
+        }
+        self.source[start..end].parse().map_err(|_| {
+            Error::SyntaxError(format!(
+                "Index {} at column {} is too large",
+                &self.source[start..end],
+                self.source[..start].chars().count() + 1
+            ))
+        })
+    }
+}
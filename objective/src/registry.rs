@@ -0,0 +1,111 @@
+use crate::class::Class;
+use crate::error::{Error, Result};
+use crate::native::Objective;
+use crate::schema::Schema;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Canonical classes by name and native type, so that every part of an application resolves a
+/// type to the same class and views built against it apply everywhere.
+#[derive(Clone, Debug)]
+pub struct Registry {
+    names: HashMap<String, Arc<dyn Class>>,
+    types: HashMap<TypeId, Arc<dyn Class>>,
+}
+
+macro_rules! primitives {
+    ($registry:ident, $($type:ident),*) => {
+        $(
+            $registry.register_native::<$type>(stringify!($type).into()).unwrap();
+        )*
+    };
+}
+
+impl Registry {
+    /// A registry of the primitive types under their Rust names.
+    pub fn new() -> Self {
+        let mut registry = Registry::empty();
+        primitives!(
+            registry, bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize,
+            f32, f64, String
+        );
+        registry
+    }
+
+    pub fn empty() -> Self {
+        Registry {
+            names: HashMap::new(),
+            types: HashMap::new(),
+        }
+    }
+
+    /// Register a class by name. Value classes are also registered by their type, which must not
+    /// already have a class.
+    pub fn register(&mut self, name: String, class: Arc<dyn Class>) -> Result<()> {
+        self.check(&name, class.value())?;
+        if let Some(type_id) = class.value() {
+            self.types.insert(type_id, class.clone());
+        }
+        self.names.insert(name, class);
+        Ok(())
+    }
+
+    /// Register the class of a native type by name and by type.
+    pub fn register_native<T: Objective>(&mut self, name: String) -> Result<()> {
+        let type_id = TypeId::of::<T>();
+        self.check(&name, Some(type_id))?;
+        let class: Arc<dyn Class> = T::class();
+        self.types.insert(type_id, class.clone());
+        self.names.insert(name, class);
+        Ok(())
+    }
+
+    /// Parse a schema whose definitions may reference any registered class, then register every
+    /// class it defines. Nothing is registered if the schema is invalid.
+    pub fn load(&mut self, source: &str) -> Result<Schema> {
+        let schema = Schema::parse_with(source, self)?;
+        for name in schema.names() {
+            self.register(name.into(), schema.get(name).unwrap().clone())?;
+        }
+        Ok(schema)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Class>> {
+        self.names.get(name)
+    }
+
+    pub fn get_native<T: 'static>(&self) -> Option<&Arc<dyn Class>> {
+        self.get_type(TypeId::of::<T>())
+    }
+
+    pub fn get_type(&self, type_id: TypeId) -> Option<&Arc<dyn Class>> {
+        self.types.get(&type_id)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains_key(name)
+    }
+
+    fn check(&self, name: &str, type_id: Option<TypeId>) -> Result<()> {
+        if let Some(existing) = self.names.get(name) {
+            return Err(Error::TypeError(format!(
+                "Name {} is already registered to {:?}!",
+                name, existing
+            )));
+        }
+        if let Some(existing) = type_id.and_then(|type_id| self.types.get(&type_id)) {
+            return Err(Error::TypeError(format!(
+                "Type of {} is already registered to {:?}!",
+                name, existing
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,484 @@
+//! A small language for defining classes at runtime, for example:
+//!
+//! ```text
+//! object Base { id: u64; }
+//! object Foo : Base {
+//!     a: i32[4];     // array
+//!     b: String[];   // list
+//!     c: Bar?;       // optional
+//! }
+//! enum Bar { none; some: f64; }
+//! ```
+//!
+//! Definitions may reference each other in any order, as well as classes from a [`Registry`], which
+//! by default only contains the primitive types under their Rust names.
+
+use crate::class::array::Array;
+use crate::class::enumeration::{self, Enum};
+use crate::class::list::List;
+use crate::class::object::{self, Object};
+use crate::class::optional::Optional;
+use crate::class::Class;
+use crate::error::{Error, Result};
+use crate::registry::Registry;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Classes defined by a schema, looked up by name.
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    names: Vec<String>,
+    classes: HashMap<String, Arc<dyn Class>>,
+}
+
+impl Schema {
+    pub fn parse(source: &str) -> Result<Schema> {
+        Schema::parse_with(source, &Registry::new())
+    }
+
+    /// Parse a schema that may reference classes from registry but not redefine them.
+    pub fn parse_with(source: &str, registry: &Registry) -> Result<Schema> {
+        let definitions = Parser::new(source)?.parse()?;
+        Resolver::new(source, &definitions, registry)?.resolve()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Class>> {
+        self.classes.get(name)
+    }
+
+    /// Names of the defined classes in declaration order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+}
+
+impl FromStr for Schema {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Schema> {
+        Schema::parse(source)
+    }
+}
+
+/// Describe a byte offset in source as a line and column, followed by the line and a caret.
+fn locate(source: &str, position: usize) -> String {
+    let start = source[..position].rfind('\n').map_or(0, |i| i + 1);
+    let end = source[position..]
+        .find('\n')
+        .map_or(source.len(), |i| position + i);
+    let line = source[..start].matches('\n').count() + 1;
+    let column = source[start..position].chars().count();
+    format!(
+        "line {}, column {}\n  {}\n  {}^",
+        line,
+        column + 1,
+        &source[start..end],
+        " ".repeat(column)
+    )
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Token<'s> {
+    Identifier(&'s str),
+    Integer(&'s str),
+    Symbol(char),
+    End,
+}
+
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Identifier(identifier) => write!(formatter, "identifier {}", identifier),
+            Token::Integer(integer) => write!(formatter, "integer {}", integer),
+            Token::Symbol(symbol) => write!(formatter, "'{}'", symbol),
+            Token::End => write!(formatter, "end of schema"),
+        }
+    }
+}
+
+struct Type<'s> {
+    name: &'s str,
+    position: usize,
+    suffixes: Vec<Suffix>,
+}
+
+enum Suffix {
+    Array(usize),
+    List,
+    Optional,
+}
+
+struct Field<'s> {
+    name: &'s str,
+    position: usize,
+    class: Option<Type<'s>>,
+}
+
+enum Kind<'s> {
+    Object {
+        base: Option<(&'s str, usize)>,
+        members: Vec<Field<'s>>,
+    },
+    Enum {
+        variants: Vec<Field<'s>>,
+    },
+}
+
+struct Definition<'s> {
+    name: &'s str,
+    position: usize,
+    kind: Kind<'s>,
+}
+
+struct Parser<'s> {
+    source: &'s str,
+    tokens: Vec<(Token<'s>, usize)>,
+    index: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn new(source: &'s str) -> Result<Self> {
+        let mut tokens = Vec::new();
+        let mut characters = source.char_indices().peekable();
+        while let Some((position, character)) = characters.next() {
+            let mut take_while = |predicate: fn(char) -> bool| {
+                let mut end = source.len();
+                while let Some(&(next, character)) = characters.peek() {
+                    if !predicate(character) {
+                        end = next;
+                        break;
+                    }
+                    characters.next();
+                }
+                &source[position..end]
+            };
+
+            if character.is_whitespace() {
+                continue;
+            } else if character == '/' && source[position..].starts_with("//") {
+                take_while(|character| character != '\n');
+            } else if character.is_alphabetic() || character == '_' {
+                let identifier =
+                    take_while(|character| character.is_alphanumeric() || character == '_');
+                tokens.push((Token::Identifier(identifier), position));
+            } else if character.is_ascii_digit() {
+                let integer = take_while(|character| character.is_ascii_digit());
+                tokens.push((Token::Integer(integer), position));
+            } else if ":;{}[]?".contains(character) {
+                tokens.push((Token::Symbol(character), position));
+            } else {
+                return Err(Error::SyntaxError(format!(
+                    "Unexpected {:?} at {}",
+                    character,
+                    locate(source, position)
+                )));
+            }
+        }
+        tokens.push((Token::End, source.len()));
+
+        Ok(Parser {
+            source,
+            tokens,
+            index: 0,
+        })
+    }
+
+    fn peek(&self) -> Token<'s> {
+        self.tokens[self.index].0
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.index].1
+    }
+
+    fn next(&mut self) -> Token<'s> {
+        let token = self.peek();
+        if token != Token::End {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn error(&self, expected: &str) -> Error {
+        Error::SyntaxError(format!(
+            "Expected {} but found {} at {}",
+            expected,
+            self.peek(),
+            locate(self.source, self.position())
+        ))
+    }
+
+    fn symbol(&mut self, symbol: char) -> Result<()> {
+        if self.peek() == Token::Symbol(symbol) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}'", symbol)))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<(&'s str, usize)> {
+        let position = self.position();
+        match self.peek() {
+            Token::Identifier(identifier) => {
+                self.next();
+                Ok((identifier, position))
+            }
+            _ => Err(self.error("identifier")),
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<Definition<'s>>> {
+        let mut definitions = Vec::new();
+        while self.peek() != Token::End {
+            definitions.push(self.definition()?);
+        }
+        Ok(definitions)
+    }
+
+    fn definition(&mut self) -> Result<Definition<'s>> {
+        let keyword = self.peek();
+        if keyword != Token::Identifier("object") && keyword != Token::Identifier("enum") {
+            return Err(self.error("'object' or 'enum'"));
+        }
+        self.next();
+        let (name, position) = self.identifier()?;
+
+        let kind = if keyword == Token::Identifier("object") {
+            let base = match self.peek() {
+                Token::Symbol(':') => {
+                    self.next();
+                    Some(self.identifier()?)
+                }
+                _ => None,
+            };
+            Kind::Object {
+                base,
+                members: self.fields(true)?,
+            }
+        } else {
+            Kind::Enum {
+                variants: self.fields(false)?,
+            }
+        };
+
+        Ok(Definition {
+            name,
+            position,
+            kind,
+        })
+    }
+
+    /// Parse `{ name: type; ... }`, where enum variants may omit the type.
+    fn fields(&mut self, typed: bool) -> Result<Vec<Field<'s>>> {
+        self.symbol('{')?;
+        let mut fields = Vec::new();
+        while self.peek() != Token::Symbol('}') {
+            let (name, position) = self.identifier()?;
+            let class = if typed || self.peek() == Token::Symbol(':') {
+                self.symbol(':')?;
+                Some(self.class()?)
+            } else {
+                None
+            };
+            self.symbol(';')?;
+            fields.push(Field {
+                name,
+                position,
+                class,
+            });
+        }
+        self.next();
+        Ok(fields)
+    }
+
+    fn class(&mut self) -> Result<Type<'s>> {
+        let (name, position) = self.identifier()?;
+        let mut suffixes = Vec::new();
+        loop {
+            match self.peek() {
+                Token::Symbol('?') => {
+                    self.next();
+                    suffixes.push(Suffix::Optional);
+                }
+                Token::Symbol('[') => {
+                    self.next();
+                    match self.peek() {
+                        Token::Symbol(']') => suffixes.push(Suffix::List),
+                        Token::Integer(integer) => {
+                            let length = integer.parse().map_err(|_| {
+                                Error::SyntaxError(format!(
+                                    "Length {} is too large at {}",
+                                    integer,
+                                    locate(self.source, self.position())
+                                ))
+                            })?;
+                            self.next();
+                            suffixes.push(Suffix::Array(length));
+                        }
+                        _ => return Err(self.error("length or ']'")),
+                    }
+                    self.symbol(']')?;
+                }
+                _ => break,
+            }
+        }
+        Ok(Type {
+            name,
+            position,
+            suffixes,
+        })
+    }
+}
+
+/// Builds classes from definitions, constructing referenced definitions first.
+struct Resolver<'s, 'd> {
+    source: &'s str,
+    registry: &'d Registry,
+    definitions: HashMap<&'s str, &'d Definition<'s>>,
+    classes: HashMap<&'s str, Arc<dyn Class>>,
+    stack: Vec<&'s str>,
+    order: Vec<&'s str>,
+}
+
+impl<'s, 'd> Resolver<'s, 'd> {
+    fn new(
+        source: &'s str,
+        definitions: &'d [Definition<'s>],
+        registry: &'d Registry,
+    ) -> Result<Self> {
+        let mut lookup = HashMap::new();
+        let mut order = Vec::new();
+        for definition in definitions {
+            if registry.contains(definition.name) || lookup.contains_key(definition.name) {
+                return Err(Error::TypeError(format!(
+                    "Class {} is already defined at {}",
+                    definition.name,
+                    locate(source, definition.position)
+                )));
+            }
+            lookup.insert(definition.name, definition);
+            order.push(definition.name);
+        }
+
+        Ok(Resolver {
+            source,
+            registry,
+            definitions: lookup,
+            classes: HashMap::new(),
+            stack: Vec::new(),
+            order,
+        })
+    }
+
+    fn resolve(mut self) -> Result<Schema> {
+        for name in self.order.clone() {
+            self.named(name, self.definitions[name].position)?;
+        }
+        let mut classes = HashMap::new();
+        for name in self.order.iter() {
+            classes.insert(name.to_string(), self.classes[name].clone());
+        }
+        Ok(Schema {
+            names: self.order.iter().map(|name| name.to_string()).collect(),
+            classes,
+        })
+    }
+
+    /// Resolve a reference to a registered or defined class made at position.
+    fn named(&mut self, name: &'s str, position: usize) -> Result<Arc<dyn Class>> {
+        if let Some(class) = self.registry.get(name).or_else(|| self.classes.get(name)) {
+            return Ok(class.clone());
+        }
+        let Some(&definition) = self.definitions.get(name) else {
+            return Err(Error::TypeError(format!(
+                "Unknown class {} at {}",
+                name,
+                locate(self.source, position)
+            )));
+        };
+
+        // Classes are immutable once built, so even cycles through lists cannot be expressed
+        if let Some(start) = self.stack.iter().position(|other| *other == name) {
+            let mut cycle = self.stack[start..].to_vec();
+            cycle.push(name);
+            return Err(Error::TypeError(format!(
+                "Class {} contains itself through {} at {}",
+                name,
+                cycle.join(" -> "),
+                locate(self.source, position)
+            )));
+        }
+
+        self.stack.push(name);
+        let class: Arc<dyn Class> = match &definition.kind {
+            Kind::Object { base, members } => {
+                let mut builder = match base {
+                    Some((base, position)) => {
+                        let base = self.object(base, *position)?;
+                        object::Builder::new_inherit(name.into(), base)
+                    }
+                    None => object::Builder::new(name.into()),
+                };
+                for member in members {
+                    let class = self.class(member.class.as_ref().unwrap())?;
+                    builder
+                        .add(member.name.into(), class)
+                        .map_err(|error| self.locate(error, member.position))?;
+                }
+                Arc::new(Object::new(builder))
+            }
+            Kind::Enum { variants } => {
+                let mut builder = enumeration::Builder::new(name.into());
+                for variant in variants {
+                    let class = match &variant.class {
+                        Some(class) => Some(self.class(class)?),
+                        None => None,
+                    };
+                    builder
+                        .add(variant.name.into(), class)
+                        .map_err(|error| self.locate(error, variant.position))?;
+                }
+                let enumeration =
+                    Enum::new(builder).map_err(|error| self.locate(error, definition.position))?;
+                Arc::new(enumeration)
+            }
+        };
+        self.stack.pop();
+
+        self.classes.insert(name, class.clone());
+        Ok(class)
+    }
+
+    fn object(&mut self, name: &'s str, position: usize) -> Result<Arc<Object>> {
+        self.named(name, position)?
+            .downcast_arc::<Object>()
+            .ok_or_else(|| {
+                Error::TypeError(format!(
+                    "Base class {} is not an object at {}",
+                    name,
+                    locate(self.source, position)
+                ))
+            })
+    }
+
+    fn class(&mut self, class: &Type<'s>) -> Result<Arc<dyn Class>> {
+        let mut resolved = self.named(class.name, class.position)?;
+        for suffix in class.suffixes.iter() {
+            resolved = match suffix {
+                Suffix::Array(length) => Arc::new(
+                    Array::new(resolved, *length)
+                        .map_err(|error| self.locate(error, class.position))?,
+                ),
+                Suffix::List => Arc::new(List::new(resolved)),
+                Suffix::Optional => Arc::new(Optional::new(resolved)),
+            };
+        }
+        Ok(resolved)
+    }
+
+    fn locate(&self, error: Error, position: usize) -> Error {
+        error.map(|message| format!("{} at {}", message, locate(self.source, position)))
+    }
+}
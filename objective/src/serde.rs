@@ -0,0 +1,442 @@
+//! Serde support for instances, enabled by the `serde` feature.
+//!
+//! Objects are represented as maps, arrays and lists as sequences, optionals as options and enums
+//! as a map with a single entry from the active variant to its payload, or null if it has none.
+//! Value types are handled by a [`Codecs`] registry since classes do not know their serde impls.
+
+use crate::class::array::Array;
+use crate::class::enumeration::Enum;
+use crate::class::list::List;
+use crate::class::object::Object;
+use crate::class::optional::Optional;
+use crate::class::{Class, Constructed};
+use crate::instance::read::{InstanceReadGuard, ReadReference};
+use crate::instance::Instance;
+use ::serde::de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess};
+use ::serde::ser::{self, SerializeMap, SerializeSeq};
+use ::serde::{Deserializer, Serialize, Serializer};
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Formatter;
+use std::sync::{Arc, OnceLock};
+
+struct Codec {
+    // Borrowing the serializable bounds the returned reference by the lifetime of its data
+    serialize: for<'s> unsafe fn(&'s Serializable<'_>) -> &'s dyn erased_serde::Serialize,
+    deserialize: for<'de> unsafe fn(
+        &mut dyn erased_serde::Deserializer<'de>,
+        *mut u8,
+    ) -> Result<(), erased_serde::Error>,
+}
+
+/// Serde implementations for value types, looked up by the `TypeId` their class reports.
+pub struct Codecs {
+    codecs: HashMap<TypeId, Codec>,
+}
+
+impl Codecs {
+    pub fn new() -> Self {
+        Codecs {
+            codecs: HashMap::new(),
+        }
+    }
+
+    /// The codecs used when none are specified, covering primitives and `String`.
+    pub fn standard() -> &'static Codecs {
+        static CODECS: OnceLock<Codecs> = OnceLock::new();
+        CODECS.get_or_init(Codecs::default)
+    }
+
+    pub fn register<T: Serialize + DeserializeOwned + 'static>(&mut self) {
+        // Invariant: only called with data of a class whose value() is T
+        unsafe fn serialize<'s, T: Serialize + 'static>(
+            serializable: &'s Serializable<'_>,
+        ) -> &'s dyn erased_serde::Serialize {
+            &*serializable.target.data.cast::<T>()
+        }
+
+        unsafe fn deserialize<T: DeserializeOwned>(
+            deserializer: &mut dyn erased_serde::Deserializer<'_>,
+            data: *mut u8,
+        ) -> Result<(), erased_serde::Error> {
+            *data.cast::<T>() = erased_serde::deserialize(deserializer)?;
+            Ok(())
+        }
+
+        self.codecs.insert(
+            TypeId::of::<T>(),
+            Codec {
+                serialize: serialize::<T>,
+                deserialize: deserialize::<T>,
+            },
+        );
+    }
+
+    fn get(&self, class: &dyn Class) -> Option<&Codec> {
+        class.value().and_then(|type_id| self.codecs.get(&type_id))
+    }
+}
+
+impl Default for Codecs {
+    fn default() -> Self {
+        let mut codecs = Codecs::new();
+        codecs.register::<bool>();
+        codecs.register::<char>();
+        codecs.register::<i8>();
+        codecs.register::<i16>();
+        codecs.register::<i32>();
+        codecs.register::<i64>();
+        codecs.register::<i128>();
+        codecs.register::<isize>();
+        codecs.register::<u8>();
+        codecs.register::<u16>();
+        codecs.register::<u32>();
+        codecs.register::<u64>();
+        codecs.register::<u128>();
+        codecs.register::<usize>();
+        codecs.register::<f32>();
+        codecs.register::<f64>();
+        codecs.register::<String>();
+        codecs
+    }
+}
+
+/// Serializes constructed data by walking its class.
+#[derive(Clone, Copy)]
+pub struct Serializable<'a> {
+    target: Constructed<'a, *const u8>,
+    codecs: &'a Codecs,
+}
+
+impl<'a> Serializable<'a> {
+    /// # Safety
+    ///
+    /// `data` must have been constructed by class and outlive the returned value.
+    pub unsafe fn new(class: &'a dyn Class, data: *const u8) -> Self {
+        Serializable {
+            target: Constructed::new(class, data),
+            codecs: Codecs::standard(),
+        }
+    }
+
+    pub fn codecs(self, codecs: &'a Codecs) -> Self {
+        Serializable { codecs, ..self }
+    }
+
+    /// # Safety
+    ///
+    /// `data` must be a child of this data, of class.
+    unsafe fn child(&self, class: &'a dyn Class, data: *const u8) -> Self {
+        Serializable {
+            target: Constructed::new(class, data),
+            codecs: self.codecs,
+        }
+    }
+}
+
+impl Serialize for Serializable<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Constructed { class, data } = self.target;
+        unsafe {
+            if let Some(codec) = self.codecs.get(class) {
+                erased_serde::serialize((codec.serialize)(self), serializer)
+            } else if let Some(object) = class.downcast_ref::<Object>() {
+                let mut map = serializer.serialize_map(Some(object.members().len()))?;
+                for member in object.members() {
+                    map.serialize_entry(
+                        &member.name,
+                        &self.child(&*member.class, data.add(member.offset)),
+                    )?;
+                }
+                map.end()
+            } else if let Some(array) = class.downcast_ref::<Array>() {
+                let stride = array.element.stride();
+                let mut seq = serializer.serialize_seq(Some(array.length))?;
+                for i in 0..array.length {
+                    seq.serialize_element(&self.child(&*array.element, data.add(stride * i)))?;
+                }
+                seq.end()
+            } else if let Some(list) = class.downcast_ref::<List>() {
+                let length = list.len(data);
+                let mut seq = serializer.serialize_seq(Some(length))?;
+                for i in 0..length {
+                    let pointer = list.item_at(data.cast_mut(), i).unwrap();
+                    seq.serialize_element(&self.child(&*list.element, pointer.data))?;
+                }
+                seq.end()
+            } else if let Some(optional) = class.downcast_ref::<Optional>() {
+                if optional.is_set(data) {
+                    serializer
+                        .serialize_some(&self.child(&*optional.inner, data.add(optional.offset)))
+                } else {
+                    serializer.serialize_none()
+                }
+            } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+                let variant = enumeration.variant(data);
+                let mut map = serializer.serialize_map(Some(1))?;
+                match &variant.class {
+                    Some(payload) => map.serialize_entry(
+                        &variant.name,
+                        &self.child(&**payload, data.add(enumeration.offset)),
+                    )?,
+                    None => map.serialize_entry(&variant.name, &())?,
+                }
+                map.end()
+            } else {
+                Err(ser::Error::custom(format!(
+                    "Class {:?} cannot be serialized!",
+                    class
+                )))
+            }
+        }
+    }
+}
+
+impl Serialize for InstanceReadGuard<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serializable().serialize(serializer)
+    }
+}
+
+impl Serialize for ReadReference<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serializable().serialize(serializer)
+    }
+}
+
+/// How deserialization treats object fields that do not match the class. By default, unknown
+/// fields are ignored and missing fields are rejected, as with serde's derives.
+#[derive(Clone, Copy, Debug)]
+pub struct Policy {
+    pub deny_unknown_fields: bool,
+    /// Members without a field keep the value they were constructed with when this is false.
+    pub deny_missing_fields: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            deny_unknown_fields: false,
+            deny_missing_fields: true,
+        }
+    }
+}
+
+/// Deserializes a freshly constructed instance of a class from any serde format.
+pub struct InstanceSeed<'a> {
+    class: Arc<dyn Class>,
+    codecs: &'a Codecs,
+    policy: Policy,
+}
+
+impl InstanceSeed<'static> {
+    pub fn new(class: Arc<dyn Class>) -> Self {
+        InstanceSeed {
+            class,
+            codecs: Codecs::standard(),
+            policy: Policy::default(),
+        }
+    }
+}
+
+impl<'a> InstanceSeed<'a> {
+    pub fn codecs<'b>(self, codecs: &'b Codecs) -> InstanceSeed<'b> {
+        InstanceSeed {
+            class: self.class,
+            codecs,
+            policy: self.policy,
+        }
+    }
+
+    pub fn policy(self, policy: Policy) -> Self {
+        InstanceSeed { policy, ..self }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for InstanceSeed<'_> {
+    type Value = Instance;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Instance, D::Error> {
+        let instance = Instance::new(self.class.clone());
+        {
+            let write = instance.write().unwrap();
+            DataSeed {
+                // The instance was just constructed by its class and the guard keeps it alive
+                target: unsafe { Constructed::new(&*self.class, write.data()) },
+                codecs: self.codecs,
+                policy: self.policy,
+            }
+            .deserialize(deserializer)?;
+        }
+        Ok(instance)
+    }
+}
+
+/// Overwrites constructed data in place, so a failure leaves every member constructed.
+#[derive(Clone, Copy)]
+struct DataSeed<'a> {
+    target: Constructed<'a, *mut u8>,
+    codecs: &'a Codecs,
+    policy: Policy,
+}
+
+impl<'a> DataSeed<'a> {
+    /// # Safety
+    ///
+    /// `data` must be a child of this data, of class.
+    unsafe fn child(&self, class: &'a dyn Class, data: *mut u8) -> Self {
+        DataSeed {
+            target: Constructed::new(class, data),
+            ..*self
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for DataSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        let Constructed { class, data } = self.target;
+        if let Some(codec) = self.codecs.get(class) {
+            let mut erased = <dyn erased_serde::Deserializer>::erase(deserializer);
+            // Invariant: data was constructed by a class whose value() matches the codec
+            unsafe { (codec.deserialize)(&mut erased, data) }.map_err(de::Error::custom)
+        } else if class.downcast_ref::<Object>().is_some() || class.downcast_ref::<Enum>().is_some()
+        {
+            deserializer.deserialize_map(self)
+        } else if class.downcast_ref::<Array>().is_some() || class.downcast_ref::<List>().is_some()
+        {
+            deserializer.deserialize_seq(self)
+        } else if class.downcast_ref::<Optional>().is_some() {
+            deserializer.deserialize_option(self)
+        } else {
+            Err(de::Error::custom(format!(
+                "Class {:?} cannot be deserialized!",
+                class
+            )))
+        }
+    }
+}
+
+impl<'de> de::Visitor<'de> for DataSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "an instance of {:?}", self.target.class)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let Constructed { class, data } = self.target;
+        unsafe {
+            if let Some(object) = class.downcast_ref::<Object>() {
+                let mut seen = HashSet::new();
+                while let Some(name) = map.next_key::<String>()? {
+                    let Some(member) = object.member(&name) else {
+                        if self.policy.deny_unknown_fields {
+                            return Err(de::Error::custom(format!(
+                                "Object of type {:?} has no attribute {}",
+                                object, name
+                            )));
+                        }
+                        map.next_value::<IgnoredAny>()?;
+                        continue;
+                    };
+                    if !seen.insert(member.name.as_str()) {
+                        return Err(de::Error::custom(format!("duplicate field `{}`", name)));
+                    }
+                    map.next_value_seed(self.child(&*member.class, data.add(member.offset)))?;
+                }
+                if self.policy.deny_missing_fields {
+                    let mut members = object.members().iter();
+                    if let Some(missing) =
+                        members.find(|member| !seen.contains(member.name.as_str()))
+                    {
+                        return Err(de::Error::custom(format!(
+                            "missing field `{}`",
+                            missing.name
+                        )));
+                    }
+                }
+                Ok(())
+            } else if let Some(enumeration) = class.downcast_ref::<Enum>() {
+                let Some(name) = map.next_key::<String>()? else {
+                    return Err(de::Error::invalid_length(0, &self));
+                };
+                enumeration.switch(data, &name).map_err(de::Error::custom)?;
+                match &enumeration.variant(data).class {
+                    Some(payload) => {
+                        map.next_value_seed(self.child(&**payload, data.add(enumeration.offset)))?
+                    }
+                    None => map.next_value::<()>()?,
+                }
+                if map.next_key::<IgnoredAny>()?.is_some() {
+                    return Err(de::Error::invalid_length(2, &self));
+                }
+                Ok(())
+            } else {
+                Err(de::Error::invalid_type(de::Unexpected::Map, &self))
+            }
+        }
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let Constructed { class, data } = self.target;
+
+        unsafe {
+            if let Some(array) = class.downcast_ref::<Array>() {
+                let stride = array.element.stride();
+                for i in 0..array.length {
+                    let seed = self.child(&*array.element, data.add(stride * i));
+                    if seq.next_element_seed(seed)?.is_none() {
+                        return Err(de::Error::invalid_length(i, &self));
+                    }
+                }
+                if seq.next_element::<IgnoredAny>()?.is_some() {
+                    return Err(de::Error::invalid_length(array.length + 1, &self));
+                }
+                Ok(())
+            } else if let Some(list) = class.downcast_ref::<List>() {
+                // Existing items are replaced rather than appended to
+                while !list.is_empty(data) {
+                    list.pop(data).unwrap();
+                }
+                loop {
+                    let element = list.push(data).map_err(de::Error::custom)?;
+                    if seq
+                        .next_element_seed(self.child(&*list.element, element))?
+                        .is_none()
+                    {
+                        list.pop(data).unwrap();
+                        return Ok(());
+                    }
+                }
+            } else {
+                Err(de::Error::invalid_type(de::Unexpected::Seq, &self))
+            }
+        }
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<(), E> {
+        match self.target.class.downcast_ref::<Optional>() {
+            Some(optional) => {
+                unsafe { optional.clear(self.target.data) };
+                Ok(())
+            }
+            None => Err(de::Error::invalid_type(de::Unexpected::Option, &self)),
+        }
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        match self.target.class.downcast_ref::<Optional>() {
+            Some(optional) => {
+                let seed = unsafe { self.child(&*optional.inner, optional.set(self.target.data)) };
+                seed.deserialize(deserializer)
+            }
+            None => Err(de::Error::invalid_type(de::Unexpected::Option, &self)),
+        }
+    }
+}